@@ -1,6 +1,6 @@
-use std::{ffi::CStr, marker::PhantomData, str::Utf8Error};
+use std::{ffi::CStr, marker::PhantomData};
 
-use crate::{impl_getters, ptr_to_cstr, struct_impl_managed};
+use crate::{cstr_to_str, impl_getters, ptr_to_cstr, struct_impl_managed, Result};
 
 use super::{Composition, Menu};
 
@@ -12,31 +12,40 @@ impl_getters! {
 }
 
 impl<'a> Context<'a> {
-    pub fn menu(&self) -> Menu {
+    pub fn menu(&self) -> Menu<'_> {
         Menu::from_raw(&self.raw.menu)
     }
 
-    pub fn composition(&self) -> Composition {
+    pub fn composition(&self) -> Composition<'_> {
         Composition::from_raw(&self.raw.composition)
     }
 }
 
 impl<'a> Context<'a> {
-    pub fn select_labels_c(&self) -> SelectLabelsCIter {
+    pub fn select_labels_c(&self) -> SelectLabelsCIter<'_> {
+        // select_labels 長度爲 page_size, 未配置時爲空指針
+        let len = if self.raw.select_labels.is_null() {
+            0
+        } else {
+            self.raw.menu.page_size.max(0) as usize
+        };
         SelectLabelsCIter {
             raw: self.raw.select_labels,
+            len,
+            index: 0,
             _phatom: PhantomData,
         }
     }
 
-    pub fn select_labels(&self) -> impl Iterator<Item = Option<Result<&str, Utf8Error>>> {
-        self.select_labels_c()
-            .map(|option| option.map(CStr::to_str))
+    pub fn select_labels(&self) -> impl Iterator<Item = Result<Option<&str>>> {
+        self.select_labels_c().map(|option| cstr_to_str!(option))
     }
 }
 
 pub struct SelectLabelsCIter<'a> {
     raw: *mut *mut i8,
+    len: usize,
+    index: usize,
     _phatom: PhantomData<&'a ()>,
 }
 
@@ -44,10 +53,10 @@ impl<'a> Iterator for SelectLabelsCIter<'a> {
     type Item = Option<&'a CStr>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(ptr) = unsafe { self.raw.as_ref() } {
-            let ptr = *ptr;
+        if self.index < self.len {
+            let ptr = unsafe { *self.raw.add(self.index) };
             let value = ptr_to_cstr!(ptr);
-            self.raw = unsafe { self.raw.offset(1) };
+            self.index += 1;
             Some(value)
         } else {
             None
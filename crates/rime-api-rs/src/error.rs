@@ -0,0 +1,70 @@
+use std::{ffi::NulError, fmt, str::Utf8Error};
+
+/// rime-api 錯誤。
+#[derive(Debug)]
+// `NulError` 沿用標準庫的名稱
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    /// 字符串含有 NUL 字節，無法傳給 librime。
+    NulError(NulError),
+    /// librime 返回的字符串不是合法 UTF-8.
+    Utf8(Utf8Error),
+    /// `rime_get_api` 返回空指針。
+    ApiUnavailable,
+    /// 未啓用 `librime` 特性，沒有鏈接 librime.
+    LibrimeDisabled,
+    /// 當前 librime 版本缺少該 api 函數。
+    MissingApiFunction(&'static str),
+    /// librime 返回失敗。
+    OperationFailed(&'static str),
+    /// 會話不存在。
+    SessionNotFound(usize),
+    /// Rime 服務線程已退出。
+    ServiceClosed,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NulError(err) => write!(f, "string contains nul byte: {err}"),
+            Error::Utf8(err) => write!(f, "invalid utf-8 from librime: {err}"),
+            Error::ApiUnavailable => write!(f, "librime api is not available"),
+            Error::LibrimeDisabled => {
+                write!(
+                    f,
+                    "built without the `librime` feature, librime is not linked"
+                )
+            }
+            Error::MissingApiFunction(name) => {
+                write!(f, "librime api function `{name}` is not available")
+            }
+            Error::OperationFailed(op) => write!(f, "librime operation `{op}` failed"),
+            Error::SessionNotFound(id) => write!(f, "rime session {id:#x} not found"),
+            Error::ServiceClosed => write!(f, "rime service has stopped"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NulError(err) => Some(err),
+            Error::Utf8(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<NulError> for Error {
+    fn from(err: NulError) -> Self {
+        Error::NulError(err)
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(err: Utf8Error) -> Self {
+        Error::Utf8(err)
+    }
+}
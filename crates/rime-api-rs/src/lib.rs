@@ -1,17 +1,26 @@
 pub use crate::{
-    api::*, candidate::*, commit::*, composition::*, config::*, context::*, menu::*,
-    schema_list::*, session::*, status::*, traits::*,
+    api::*, candidate::*, commit::*, composition::*, config::*, context::*, error::*, menu::*,
+    schema_list::*, service::*, session::*, snapshot::*, status::*, traits::*,
 };
 
+#[cfg(feature = "mock")]
+pub use crate::mock::*;
+
 mod api;
 mod candidate;
 mod commit;
 mod composition;
 mod config;
 mod context;
+mod error;
 mod menu;
+#[cfg(feature = "mock")]
+mod mock;
 mod schema_list;
+mod service;
 mod session;
+mod snapshot;
 mod status;
+pub mod sys;
 mod traits;
 mod util;
@@ -1,7 +1,6 @@
 use std::marker::PhantomData;
 
-use librime_sys::RimeCandidate;
-
+use crate::sys::RimeCandidate;
 use crate::{impl_getters, struct_impl_reference, Candidate};
 
 struct_impl_reference!(Menu);
@@ -23,7 +22,7 @@ impl_getters! {
 }
 
 impl<'a> Menu<'a> {
-    pub fn candidates(&self) -> CandidateIter {
+    pub fn candidates(&self) -> CandidateIter<'a> {
         CandidateIter {
             raw: self.raw.candidates,
             len: self.raw.num_candidates as isize,
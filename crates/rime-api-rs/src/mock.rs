@@ -0,0 +1,820 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    ptr::{self, NonNull},
+};
+
+use crate::sys::{
+    rime_api_t, Bool, RimeCandidate, RimeCommit, RimeComposition, RimeContext, RimeKeyCode,
+    RimeKeyCode_XK_BackSpace, RimeKeyCode_XK_Down, RimeKeyCode_XK_Escape, RimeKeyCode_XK_Page_Down,
+    RimeKeyCode_XK_Page_Up, RimeKeyCode_XK_Return, RimeKeyCode_XK_Up, RimeKeyCode_XK_equal,
+    RimeKeyCode_XK_minus, RimeKeyCode_XK_space, RimeMenu, RimeModifier_kAltMask,
+    RimeModifier_kControlMask, RimeModifier_kReleaseMask, RimeModifier_kSuperMask,
+    RimeNotificationHandler, RimeSessionId, RimeStatus, RimeTraits,
+};
+use crate::{CandidateSnapshot, Rime};
+
+/// 模擬詞典：編碼到候選詞的映射。
+#[derive(Clone, Debug)]
+pub struct MockDictionary {
+    entries: HashMap<String, Vec<CandidateSnapshot>>,
+    page_size: usize,
+    schema_id: String,
+    schema_name: String,
+}
+
+impl Default for MockDictionary {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            page_size: 5,
+            schema_id: "mock".to_string(),
+            schema_name: "Mock".to_string(),
+        }
+    }
+}
+
+impl MockDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加編碼及其候選詞。
+    pub fn entry<I>(mut self, code: &str, candidates: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<CandidateSnapshot>,
+    {
+        let candidates = candidates.into_iter().map(Into::into).collect();
+        self.entries.insert(code.to_string(), candidates);
+        self
+    }
+
+    /// 設置每頁候選數量。
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// 設置方案編號和名稱。
+    pub fn schema(mut self, schema_id: &str, schema_name: &str) -> Self {
+        self.schema_id = schema_id.to_string();
+        self.schema_name = schema_name.to_string();
+        self
+    }
+}
+
+impl From<&str> for CandidateSnapshot {
+    fn from(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            comment: None,
+        }
+    }
+}
+
+impl From<(&str, &str)> for CandidateSnapshot {
+    fn from((text, comment): (&str, &str)) -> Self {
+        Self {
+            text: text.to_string(),
+            comment: Some(comment.to_string()),
+        }
+    }
+}
+
+impl Rime {
+    /// 創建模擬後端。
+    ///
+    /// 不調用 librime, 無需 rime 數據。狀態保存在當前線程，
+    /// 故須在使用 api 的線程上創建，例如 [`RimeService::spawn`](crate::RimeService::spawn) 的 `init` 中。
+    pub fn mock(dictionary: MockDictionary) -> Self {
+        STATE.with_borrow_mut(|state| *state = Some(MockState::new(dictionary)));
+        Self::from_raw(NonNull::from(&MOCK_API))
+    }
+}
+
+struct MockSession {
+    input: String,
+    input_c: CString,
+    caret: usize,
+    page_no: usize,
+    /// 高亮候選的絕對位置。
+    highlighted: usize,
+    options: HashMap<String, bool>,
+    commit: Option<String>,
+    schema_id: String,
+}
+
+struct MockState {
+    dictionary: MockDictionary,
+    sessions: HashMap<RimeSessionId, MockSession>,
+    next_id: RimeSessionId,
+}
+
+thread_local! {
+    static STATE: RefCell<Option<MockState>> = const { RefCell::new(None) };
+}
+
+impl MockState {
+    fn new(dictionary: MockDictionary) -> Self {
+        Self {
+            dictionary,
+            sessions: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    fn candidates(&self, input: &str) -> &[CandidateSnapshot] {
+        self.dictionary
+            .entries
+            .get(input)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+impl MockSession {
+    fn set_input(&mut self, input: String) {
+        self.caret = input.len();
+        self.input = input;
+        self.page_no = 0;
+        self.highlighted = 0;
+    }
+
+    fn ascii_mode(&self) -> bool {
+        self.options.get("ascii_mode").copied().unwrap_or(false)
+    }
+}
+
+/// 對會話運行 `f`, 會話不存在時返回 `None`.
+fn with_session<R>(
+    session_id: RimeSessionId,
+    f: impl FnOnce(&mut MockSession, &mut MockDictionary) -> R,
+) -> Option<R> {
+    STATE.with_borrow_mut(|state| {
+        let state = state.as_mut()?;
+        let session = state.sessions.get_mut(&session_id)?;
+        Some(f(session, &mut state.dictionary))
+    })
+}
+
+fn to_bool(value: Option<bool>) -> Bool {
+    value.unwrap_or(false) as Bool
+}
+
+fn into_raw(s: &str) -> *mut c_char {
+    CString::new(s).unwrap_or_default().into_raw()
+}
+
+unsafe fn free_raw(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        drop(CString::from_raw(ptr));
+    }
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> &'a str {
+    if ptr.is_null() {
+        ""
+    } else {
+        CStr::from_ptr(ptr).to_str().unwrap_or_default()
+    }
+}
+
+/// 將 `src` 寫入長度爲 `len` 的緩衝區。
+unsafe fn write_buf(buf: *mut c_char, len: usize, src: &str) {
+    if buf.is_null() || len == 0 {
+        return;
+    }
+    let n = src.len().min(len - 1);
+    ptr::copy_nonoverlapping(src.as_ptr().cast(), buf, n);
+    *buf.add(n) = 0;
+}
+
+static MOCK_API: rime_api_t = rime_api_t {
+    data_size: (std::mem::size_of::<rime_api_t>() - std::mem::size_of::<c_int>()) as c_int,
+    setup: Some(setup),
+    set_notification_handler: Some(set_notification_handler),
+    initialize: Some(setup),
+    finalize: Some(finalize),
+    start_maintenance: Some(start_maintenance),
+    is_maintenance_mode: Some(is_maintenance_mode),
+    join_maintenance_thread: Some(join_maintenance_thread),
+    create_session: Some(create_session),
+    find_session: Some(find_session),
+    destroy_session: Some(destroy_session),
+    process_key: Some(process_key),
+    commit_composition: Some(commit_composition),
+    clear_composition: Some(clear_composition),
+    get_commit: Some(get_commit),
+    free_commit: Some(free_commit),
+    get_context: Some(get_context),
+    free_context: Some(free_context),
+    get_status: Some(get_status),
+    free_status: Some(free_status),
+    set_option: Some(set_option),
+    get_option: Some(get_option),
+    get_current_schema: Some(get_current_schema),
+    select_schema: Some(select_schema),
+    simulate_key_sequence: Some(simulate_key_sequence),
+    get_input: Some(get_input),
+    set_input: Some(set_input),
+    get_caret_pos: Some(get_caret_pos),
+    set_caret_pos: Some(set_caret_pos),
+    select_candidate: Some(select_candidate),
+    select_candidate_on_current_page: Some(select_candidate_on_current_page),
+    delete_candidate: Some(delete_candidate),
+    delete_candidate_on_current_page: Some(delete_candidate_on_current_page),
+    highlight_candidate: Some(highlight_candidate),
+    highlight_candidate_on_current_page: Some(highlight_candidate_on_current_page),
+    change_page: Some(change_page),
+    get_version: Some(get_version),
+    // 其餘函數不模擬，調用時返回 MissingApiFunction
+    ..unsafe { std::mem::zeroed() }
+};
+
+/// Entry.
+unsafe extern "C" fn setup(_traits: *mut RimeTraits) {}
+
+unsafe extern "C" fn set_notification_handler(
+    _handler: RimeNotificationHandler,
+    _context_object: *mut c_void,
+) {
+}
+
+unsafe extern "C" fn finalize() {
+    STATE.with_borrow_mut(|state| *state = None);
+}
+
+unsafe extern "C" fn start_maintenance(_full_check: Bool) -> Bool {
+    0
+}
+
+unsafe extern "C" fn is_maintenance_mode() -> Bool {
+    0
+}
+
+unsafe extern "C" fn join_maintenance_thread() {}
+
+unsafe extern "C" fn get_version() -> *const c_char {
+    c"mock".as_ptr()
+}
+
+/// Session management.
+unsafe extern "C" fn create_session() -> RimeSessionId {
+    STATE.with_borrow_mut(|state| {
+        let Some(state) = state.as_mut() else {
+            return 0;
+        };
+        let id = state.next_id;
+        state.next_id += 1;
+        let session = MockSession {
+            input: String::new(),
+            input_c: CString::default(),
+            caret: 0,
+            page_no: 0,
+            highlighted: 0,
+            options: HashMap::new(),
+            commit: None,
+            schema_id: state.dictionary.schema_id.clone(),
+        };
+        state.sessions.insert(id, session);
+        id
+    })
+}
+
+unsafe extern "C" fn find_session(session_id: RimeSessionId) -> Bool {
+    to_bool(with_session(session_id, |_, _| true))
+}
+
+unsafe extern "C" fn destroy_session(session_id: RimeSessionId) -> Bool {
+    STATE.with_borrow_mut(|state| {
+        let removed = state
+            .as_mut()
+            .and_then(|state| state.sessions.remove(&session_id));
+        removed.is_some() as Bool
+    })
+}
+
+/// 輸入。
+unsafe extern "C" fn process_key(session_id: RimeSessionId, keycode: c_int, mask: c_int) -> Bool {
+    let blocked = RimeModifier_kReleaseMask
+        | RimeModifier_kControlMask
+        | RimeModifier_kAltMask
+        | RimeModifier_kSuperMask;
+    if mask as u32 & blocked != 0 {
+        return 0;
+    }
+    to_bool(with_session(session_id, |session, dictionary| {
+        process(session, dictionary, keycode as RimeKeyCode)
+    }))
+}
+
+// bindgen 生成的鍵碼常量不是大寫
+#[allow(non_upper_case_globals)]
+fn process(
+    session: &mut MockSession,
+    dictionary: &mut MockDictionary,
+    keycode: RimeKeyCode,
+) -> bool {
+    if session.ascii_mode() {
+        return false;
+    }
+    let page_size = dictionary.page_size;
+    let total = dictionary.entries.get(&session.input).map_or(0, Vec::len);
+    // 小寫字母開始或繼續組合
+    if let Some(c) = char::from_u32(keycode).filter(char::is_ascii_lowercase) {
+        let mut input = std::mem::take(&mut session.input);
+        input.push(c);
+        session.set_input(input);
+        return true;
+    }
+    // 未組合時不處理其他按鍵
+    if session.input.is_empty() {
+        return false;
+    }
+    match keycode {
+        RimeKeyCode_XK_BackSpace => {
+            let mut input = std::mem::take(&mut session.input);
+            input.pop();
+            session.set_input(input);
+        }
+        RimeKeyCode_XK_Escape => session.set_input(String::new()),
+        RimeKeyCode_XK_space => {
+            let index = session.highlighted;
+            commit_candidate(session, dictionary, index);
+        }
+        RimeKeyCode_XK_Return => {
+            session.commit = Some(std::mem::take(&mut session.input));
+            session.set_input(String::new());
+        }
+        RimeKeyCode_XK_Up => session.highlighted = session.highlighted.saturating_sub(1),
+        RimeKeyCode_XK_Down => {
+            if session.highlighted + 1 < total {
+                session.highlighted += 1;
+            }
+        }
+        RimeKeyCode_XK_Page_Up | RimeKeyCode_XK_minus => {
+            page(session, page_size, total, true);
+        }
+        RimeKeyCode_XK_Page_Down | RimeKeyCode_XK_equal => {
+            page(session, page_size, total, false);
+        }
+        _ => {
+            // 數字鍵選擇當前頁候選
+            if let Some(digit) = char::from_u32(keycode).and_then(|c| c.to_digit(10)) {
+                let index = session.page_no * page_size + (digit as usize).wrapping_sub(1);
+                if (1..=page_size).contains(&(digit as usize)) && index < total {
+                    commit_candidate(session, dictionary, index);
+                }
+            }
+        }
+    }
+    // 組合中的其他按鍵均被吞掉
+    session.highlighted = session.highlighted.min(total.saturating_sub(1));
+    session.page_no = session.highlighted / page_size;
+    true
+}
+
+/// 提交候選詞，無候選時提交原始編碼。
+fn commit_candidate(session: &mut MockSession, dictionary: &MockDictionary, index: usize) -> bool {
+    let text = match dictionary.entries.get(&session.input) {
+        Some(candidates) => match candidates.get(index) {
+            Some(candidate) => candidate.text.clone(),
+            None => return false,
+        },
+        None => session.input.clone(),
+    };
+    session.commit = Some(text);
+    session.set_input(String::new());
+    true
+}
+
+/// 翻頁，返回是否翻頁。
+fn page(session: &mut MockSession, page_size: usize, total: usize, backward: bool) -> bool {
+    if backward && session.page_no > 0 {
+        session.page_no -= 1;
+    } else if !backward && (session.page_no + 1) * page_size < total {
+        session.page_no += 1;
+    } else {
+        return false;
+    }
+    session.highlighted = session.page_no * page_size;
+    true
+}
+
+unsafe extern "C" fn commit_composition(session_id: RimeSessionId) -> Bool {
+    to_bool(with_session(session_id, |session, _| {
+        if session.input.is_empty() {
+            return false;
+        }
+        session.commit = Some(std::mem::take(&mut session.input));
+        session.set_input(String::new());
+        true
+    }))
+}
+
+unsafe extern "C" fn clear_composition(session_id: RimeSessionId) {
+    with_session(session_id, |session, _| session.set_input(String::new()));
+}
+
+unsafe extern "C" fn simulate_key_sequence(
+    session_id: RimeSessionId,
+    key_sequence: *const c_char,
+) -> Bool {
+    let mut rest = str_arg(key_sequence);
+    while let Some(c) = rest.chars().next() {
+        let keycode = if let Some(name) = rest.strip_prefix('{').and_then(|s| s.split_once('}')) {
+            rest = name.1;
+            match name.0 {
+                "space" => RimeKeyCode_XK_space,
+                "Return" => RimeKeyCode_XK_Return,
+                "BackSpace" => RimeKeyCode_XK_BackSpace,
+                "Escape" => RimeKeyCode_XK_Escape,
+                "Up" => RimeKeyCode_XK_Up,
+                "Down" => RimeKeyCode_XK_Down,
+                "Page_Up" => RimeKeyCode_XK_Page_Up,
+                "Page_Down" => RimeKeyCode_XK_Page_Down,
+                _ => return 0,
+            }
+        } else {
+            rest = &rest[c.len_utf8()..];
+            c as RimeKeyCode
+        };
+        process_key(session_id, keycode as c_int, 0);
+    }
+    to_bool(with_session(session_id, |_, _| true))
+}
+
+unsafe extern "C" fn get_input(session_id: RimeSessionId) -> *const c_char {
+    with_session(session_id, |session, _| {
+        session.input_c = CString::new(session.input.as_str()).unwrap_or_default();
+        session.input_c.as_ptr()
+    })
+    .unwrap_or(ptr::null())
+}
+
+unsafe extern "C" fn set_input(session_id: RimeSessionId, input: *const c_char) -> Bool {
+    let input = str_arg(input).to_string();
+    to_bool(with_session(session_id, |session, _| {
+        session.set_input(input);
+        true
+    }))
+}
+
+unsafe extern "C" fn get_caret_pos(session_id: RimeSessionId) -> usize {
+    with_session(session_id, |session, _| session.caret).unwrap_or(0)
+}
+
+unsafe extern "C" fn set_caret_pos(session_id: RimeSessionId, caret_pos: usize) {
+    with_session(session_id, |session, _| {
+        session.caret = caret_pos.min(session.input.len())
+    });
+}
+
+/// 輸出。
+unsafe extern "C" fn get_commit(session_id: RimeSessionId, commit: *mut RimeCommit) -> Bool {
+    let text = with_session(session_id, |session, _| session.commit.take()).flatten();
+    match (text, commit.as_mut()) {
+        (Some(text), Some(commit)) => {
+            commit.text = into_raw(&text);
+            1
+        }
+        _ => 0,
+    }
+}
+
+unsafe extern "C" fn free_commit(commit: *mut RimeCommit) -> Bool {
+    let Some(commit) = commit.as_mut() else {
+        return 0;
+    };
+    free_raw(commit.text);
+    commit.text = ptr::null_mut();
+    1
+}
+
+unsafe extern "C" fn get_context(session_id: RimeSessionId, context: *mut RimeContext) -> Bool {
+    let Some(context) = context.as_mut() else {
+        return 0;
+    };
+    let filled = STATE.with_borrow(|state| {
+        let state = state.as_ref()?;
+        let session = state.sessions.get(&session_id)?;
+        let page_size = state.dictionary.page_size;
+        let candidates = state.candidates(&session.input);
+        let start = (session.page_no * page_size).min(candidates.len());
+        let end = (start + page_size).min(candidates.len());
+        let page = &candidates[start..end];
+        let raw_candidates = page
+            .iter()
+            .map(|candidate| RimeCandidate {
+                text: into_raw(&candidate.text),
+                comment: candidate
+                    .comment
+                    .as_deref()
+                    .map_or(ptr::null_mut(), into_raw),
+                reserved: ptr::null_mut(),
+            })
+            .collect::<Box<[_]>>();
+
+        let composing = !session.input.is_empty();
+        let len = session.input.len() as c_int;
+        context.composition = RimeComposition {
+            length: len,
+            cursor_pos: session.caret as c_int,
+            sel_start: 0,
+            sel_end: len,
+            preedit: if composing {
+                into_raw(&session.input)
+            } else {
+                ptr::null_mut()
+            },
+        };
+        context.menu = RimeMenu {
+            page_size: page_size as c_int,
+            page_no: session.page_no as c_int,
+            is_last_page: (end == candidates.len()) as Bool,
+            highlighted_candidate_index: (session.highlighted - start.min(session.highlighted))
+                as c_int,
+            num_candidates: raw_candidates.len() as c_int,
+            candidates: Box::into_raw(raw_candidates).cast(),
+            select_keys: ptr::null_mut(),
+        };
+        context.commit_text_preview = candidates
+            .get(session.highlighted)
+            .map_or(ptr::null_mut(), |candidate| into_raw(&candidate.text));
+        context.select_labels = ptr::null_mut();
+        Some(())
+    });
+    filled.is_some() as Bool
+}
+
+unsafe extern "C" fn free_context(context: *mut RimeContext) -> Bool {
+    let Some(context) = context.as_mut() else {
+        return 0;
+    };
+    free_raw(context.composition.preedit);
+    free_raw(context.commit_text_preview);
+    let menu = &mut context.menu;
+    if !menu.candidates.is_null() {
+        let len = menu.num_candidates as usize;
+        let candidates = Box::from_raw(ptr::slice_from_raw_parts_mut(menu.candidates, len));
+        for candidate in candidates.iter() {
+            free_raw(candidate.text);
+            free_raw(candidate.comment);
+        }
+    }
+    *context = RimeContext {
+        data_size: context.data_size,
+        ..std::mem::zeroed()
+    };
+    1
+}
+
+unsafe extern "C" fn get_status(session_id: RimeSessionId, status: *mut RimeStatus) -> Bool {
+    let Some(status) = status.as_mut() else {
+        return 0;
+    };
+    let filled = STATE.with_borrow(|state| {
+        let state = state.as_ref()?;
+        let session = state.sessions.get(&session_id)?;
+        let option = |name: &str| session.options.get(name).copied().unwrap_or(false) as Bool;
+        status.schema_id = into_raw(&session.schema_id);
+        status.schema_name = into_raw(&state.dictionary.schema_name);
+        status.is_disabled = 0;
+        status.is_composing = !session.input.is_empty() as Bool;
+        status.is_ascii_mode = option("ascii_mode");
+        status.is_full_shape = option("full_shape");
+        status.is_simplified = option("simplification");
+        status.is_traditional = !option("simplification") as Bool;
+        status.is_ascii_punct = option("ascii_punct");
+        Some(())
+    });
+    filled.is_some() as Bool
+}
+
+unsafe extern "C" fn free_status(status: *mut RimeStatus) -> Bool {
+    let Some(status) = status.as_mut() else {
+        return 0;
+    };
+    free_raw(status.schema_id);
+    free_raw(status.schema_name);
+    status.schema_id = ptr::null_mut();
+    status.schema_name = ptr::null_mut();
+    1
+}
+
+/// 運行時選項。
+unsafe extern "C" fn set_option(session_id: RimeSessionId, option: *const c_char, value: Bool) {
+    let option = str_arg(option).to_string();
+    with_session(session_id, |session, _| {
+        session.options.insert(option, value != 0)
+    });
+}
+
+unsafe extern "C" fn get_option(session_id: RimeSessionId, option: *const c_char) -> Bool {
+    let option = str_arg(option);
+    to_bool(with_session(session_id, |session, _| {
+        session.options.get(option).copied().unwrap_or(false)
+    }))
+}
+
+unsafe extern "C" fn get_current_schema(
+    session_id: RimeSessionId,
+    schema_id: *mut c_char,
+    buffer_size: usize,
+) -> Bool {
+    to_bool(with_session(session_id, |session, _| {
+        write_buf(schema_id, buffer_size, &session.schema_id);
+        true
+    }))
+}
+
+unsafe extern "C" fn select_schema(session_id: RimeSessionId, schema_id: *const c_char) -> Bool {
+    let schema_id = str_arg(schema_id).to_string();
+    to_bool(with_session(session_id, |session, _| {
+        session.schema_id = schema_id;
+        session.set_input(String::new());
+        true
+    }))
+}
+
+/// 候選和翻頁。
+unsafe extern "C" fn select_candidate(session_id: RimeSessionId, index: usize) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        commit_candidate(session, dictionary, index)
+    }))
+}
+
+unsafe extern "C" fn select_candidate_on_current_page(
+    session_id: RimeSessionId,
+    index: usize,
+) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        if index >= dictionary.page_size {
+            return false;
+        }
+        let index = session.page_no * dictionary.page_size + index;
+        commit_candidate(session, dictionary, index)
+    }))
+}
+
+/// 從詞典刪除候選詞。
+fn delete(session: &mut MockSession, dictionary: &mut MockDictionary, index: usize) -> bool {
+    let Some(candidates) = dictionary.entries.get_mut(&session.input) else {
+        return false;
+    };
+    if index >= candidates.len() {
+        return false;
+    }
+    candidates.remove(index);
+    session.highlighted = session.highlighted.min(candidates.len().saturating_sub(1));
+    session.page_no = session.highlighted / dictionary.page_size;
+    true
+}
+
+unsafe extern "C" fn delete_candidate(session_id: RimeSessionId, index: usize) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        delete(session, dictionary, index)
+    }))
+}
+
+unsafe extern "C" fn delete_candidate_on_current_page(
+    session_id: RimeSessionId,
+    index: usize,
+) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        if index >= dictionary.page_size {
+            return false;
+        }
+        let index = session.page_no * dictionary.page_size + index;
+        delete(session, dictionary, index)
+    }))
+}
+
+/// 高亮候選詞。
+fn highlight(session: &mut MockSession, dictionary: &MockDictionary, index: usize) -> bool {
+    let total = dictionary.entries.get(&session.input).map_or(0, Vec::len);
+    if index >= total {
+        return false;
+    }
+    session.highlighted = index;
+    session.page_no = index / dictionary.page_size;
+    true
+}
+
+unsafe extern "C" fn highlight_candidate(session_id: RimeSessionId, index: usize) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        highlight(session, dictionary, index)
+    }))
+}
+
+unsafe extern "C" fn highlight_candidate_on_current_page(
+    session_id: RimeSessionId,
+    index: usize,
+) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        if index >= dictionary.page_size {
+            return false;
+        }
+        let index = session.page_no * dictionary.page_size + index;
+        highlight(session, dictionary, index)
+    }))
+}
+
+unsafe extern "C" fn change_page(session_id: RimeSessionId, backward: Bool) -> Bool {
+    to_bool(with_session(session_id, |session, dictionary| {
+        let total = dictionary.entries.get(&session.input).map_or(0, Vec::len);
+        page(session, dictionary.page_size, total, backward != 0)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RimeService;
+
+    fn dictionary() -> MockDictionary {
+        MockDictionary::new()
+            .page_size(2)
+            .entry("nihao", ["你好", "擬好", "你號"])
+            .entry("ni", [("你", "nǐ"), ("泥", "ní")])
+    }
+
+    #[test]
+    fn type_and_commit() {
+        let api = Rime::mock(dictionary());
+        let session = api.create_session().unwrap();
+        session.simulate_key_sequence("nihao").unwrap();
+
+        let context = session.context_snapshot().unwrap();
+        assert_eq!(context.composition.preedit.as_deref(), Some("nihao"));
+        assert_eq!(context.menu.candidates.len(), 2);
+        assert!(!context.menu.is_last_page);
+        assert_eq!(context.commit_text_preview.as_deref(), Some("你好"));
+        assert!(session.status_snapshot().unwrap().is_composing);
+
+        session.simulate_key_sequence("{space}").unwrap();
+        let commit = session.commit_snapshot().unwrap();
+        assert_eq!(commit.text.as_deref(), Some("你好"));
+        assert!(!session.status_snapshot().unwrap().is_composing);
+    }
+
+    #[test]
+    fn paging_and_selection() {
+        let api = Rime::mock(dictionary());
+        let session = api.create_session().unwrap();
+        session.simulate_key_sequence("nihao").unwrap();
+
+        assert!(session.change_page(false).unwrap());
+        let menu = session.context_snapshot().unwrap().menu;
+        assert_eq!(menu.page_no, 1);
+        assert!(menu.is_last_page);
+        assert_eq!(menu.candidates[0].text, "你號");
+        assert!(!session.change_page(false).unwrap());
+
+        session.select_candidate_on_current_page(0).unwrap();
+        let commit = session.commit_snapshot().unwrap();
+        assert_eq!(commit.text.as_deref(), Some("你號"));
+    }
+
+    #[test]
+    fn comments_and_delete() {
+        let api = Rime::mock(dictionary());
+        let session = api.create_session().unwrap();
+        session.simulate_key_sequence("ni").unwrap();
+        let menu = session.context_snapshot().unwrap().menu;
+        assert_eq!(menu.candidates[1].comment.as_deref(), Some("ní"));
+
+        session.delete_candidate_on_current_page(0).unwrap();
+        let menu = session.context_snapshot().unwrap().menu;
+        assert_eq!(menu.candidates.len(), 1);
+        assert_eq!(menu.candidates[0].text, "泥");
+    }
+
+    #[test]
+    fn ascii_mode_passes_keys() {
+        let api = Rime::mock(dictionary());
+        let session = api.create_session().unwrap();
+        session.set_option("ascii_mode", true).unwrap();
+        assert!(!session.process_key('n' as i32, 0).unwrap());
+        assert!(session.status_snapshot().unwrap().is_ascii_mode);
+    }
+
+    #[test]
+    fn service_runs_mock_on_worker() {
+        let handle = RimeService::spawn(|| Ok(Rime::mock(dictionary()))).unwrap();
+        let session = handle.create_session().unwrap();
+        let other = handle.clone();
+        std::thread::spawn(move || other.simulate_key_sequence(session, "ni").unwrap())
+            .join()
+            .unwrap();
+        let context = handle.context(session).unwrap();
+        assert_eq!(context.menu.candidates[0].text, "你");
+        handle.destroy_session(session).unwrap();
+        assert!(matches!(
+            handle.context(session),
+            Err(crate::Error::SessionNotFound(_))
+        ));
+    }
+}
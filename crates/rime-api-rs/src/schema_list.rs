@@ -1,7 +1,6 @@
 use std::marker::PhantomData;
 
-use librime_sys::RimeSchemaListItem;
-
+use crate::sys::RimeSchemaListItem;
 use crate::{impl_getters, struct_impl_managed, struct_impl_reference};
 
 struct_impl_managed!(SchemaList);
@@ -13,7 +12,7 @@ impl_getters! {
 }
 
 impl<'a> SchemaList<'a> {
-    pub fn list(&self) -> SchemaListItemIter {
+    pub fn list(&self) -> SchemaListItemIter<'_> {
         SchemaListItemIter {
             raw: self.raw.list,
             len: self.raw.size,
@@ -41,7 +40,7 @@ impl<'a> Iterator for SchemaListItemIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index < self.len {
-            let ptr = unsafe { self.raw.offset(self.index as isize) };
+            let ptr = unsafe { self.raw.add(self.index) };
             let value = unsafe { ptr.as_ref().map(SchemaListItem::from_raw) };
             self.index += 1;
             Some(value)
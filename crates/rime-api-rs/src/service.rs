@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use crate::{CommitSnapshot, ContextSnapshot, Error, Result, Rime, Session, StatusSnapshot};
+
+/// 工作線程上的會話表。
+pub type Sessions<'a> = HashMap<SessionId, Session<'a>>;
+
+type Job = Box<dyn for<'a> FnOnce(&'a Rime, &mut Sessions<'a>) + Send>;
+
+/// 服務中的會話編號。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SessionId(pub usize);
+
+/// 在專用線程上運行的 Rime 服務。
+///
+/// `Rime` 和 `Session` 持有 librime 的裸指針，不是 `Send`, 只能在創建它們的線程上使用。
+/// 服務線程獨佔 api 和所有會話，其他線程通過 [`RimeHandle`] 發送請求並取回快照。
+pub struct RimeService;
+
+impl RimeService {
+    /// 啓動服務線程。
+    ///
+    /// `init` 在服務線程上運行，負責創建並初始化 `Rime`, 其錯誤會在此返回。
+    /// 所有 [`RimeHandle`] 被丟棄後，服務線程銷毀會話並結束 librime.
+    pub fn spawn<F>(init: F) -> Result<RimeHandle>
+    where
+        F: FnOnce() -> Result<Rime> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("rime".to_string())
+            .spawn(move || match init() {
+                Ok(api) => {
+                    let _ = ready_sender.send(Ok(()));
+                    run(api, receiver);
+                }
+                Err(err) => {
+                    let _ = ready_sender.send(Err(err));
+                }
+            })
+            .map_err(|_| Error::OperationFailed("spawn"))?;
+        ready_receiver.recv().map_err(|_| Error::ServiceClosed)??;
+        Ok(RimeHandle { sender })
+    }
+}
+
+/// 服務線程主循環。
+fn run(api: Rime, receiver: Receiver<Job>) {
+    let mut sessions = Sessions::new();
+    for job in receiver {
+        job(&api, &mut sessions);
+    }
+    // 會話須先於 api 銷毀
+    drop(sessions);
+}
+
+/// Rime 服務句柄，`Send + Sync`, 可複製到任意線程。
+///
+/// 所有請求在服務線程上依次執行，調用方阻塞直至返回。
+#[derive(Clone)]
+pub struct RimeHandle {
+    sender: Sender<Job>,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RimeHandle>();
+};
+
+/// 通用請求。
+impl RimeHandle {
+    /// 在服務線程上運行 `f`, 並返回其結果。
+    pub fn call<F, R>(&self, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&'a Rime, &mut Sessions<'a>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply_sender, reply_receiver) = mpsc::channel();
+        let job: Job = Box::new(move |api, sessions| {
+            let _ = reply_sender.send(f(api, sessions));
+        });
+        self.sender.send(job).map_err(|_| Error::ServiceClosed)?;
+        reply_receiver.recv().map_err(|_| Error::ServiceClosed)?
+    }
+
+    /// 在服務線程上以指定會話運行 `f`.
+    pub fn with_session<F, R>(&self, id: SessionId, f: F) -> Result<R>
+    where
+        F: for<'a> FnOnce(&Session<'a>) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call(move |_, sessions| {
+            let session = sessions.get(&id).ok_or(Error::SessionNotFound(id.0))?;
+            f(session)
+        })
+    }
+}
+
+/// 會話管理。
+impl RimeHandle {
+    pub fn create_session(&self) -> Result<SessionId> {
+        self.call(|api, sessions| {
+            let session = api.create_session()?;
+            let id = SessionId(session.id());
+            sessions.insert(id, session);
+            Ok(id)
+        })
+    }
+
+    pub fn destroy_session(&self, id: SessionId) -> Result<()> {
+        self.call(move |_, sessions| {
+            sessions
+                .remove(&id)
+                .map(drop)
+                .ok_or(Error::SessionNotFound(id.0))
+        })
+    }
+}
+
+/// 輸入。
+impl RimeHandle {
+    /// 處理按鍵，返回是否被 Rime 處理。
+    pub fn process_key(&self, id: SessionId, keycode: i32, mask: i32) -> Result<bool> {
+        self.with_session(id, move |session| session.process_key(keycode, mask))
+    }
+
+    /// 提交組合，返回是否有內容提交。
+    pub fn commit_composition(&self, id: SessionId) -> Result<bool> {
+        self.with_session(id, |session| session.commit_composition())
+    }
+
+    pub fn clear_composition(&self, id: SessionId) -> Result<()> {
+        self.with_session(id, |session| session.clear_composition())
+    }
+
+    pub fn simulate_key_sequence(
+        &self,
+        id: SessionId,
+        key_sequence: impl Into<Vec<u8>>,
+    ) -> Result<()> {
+        let key_sequence = CString::new(key_sequence)?;
+        self.with_session(id, move |session| {
+            session.simulate_key_sequence_c(&key_sequence)
+        })
+    }
+}
+
+/// 輸出。
+impl RimeHandle {
+    pub fn commit(&self, id: SessionId) -> Result<CommitSnapshot> {
+        self.with_session(id, |session| session.commit_snapshot())
+    }
+
+    pub fn context(&self, id: SessionId) -> Result<ContextSnapshot> {
+        self.with_session(id, |session| session.context_snapshot())
+    }
+
+    pub fn status(&self, id: SessionId) -> Result<StatusSnapshot> {
+        self.with_session(id, |session| session.status_snapshot())
+    }
+}
+
+/// 運行時選項。
+impl RimeHandle {
+    pub fn set_option(&self, id: SessionId, option: impl Into<Vec<u8>>, value: bool) -> Result<()> {
+        let option = CString::new(option)?;
+        self.with_session(id, move |session| session.set_option_c(&option, value))
+    }
+
+    pub fn get_option(&self, id: SessionId, option: impl Into<Vec<u8>>) -> Result<bool> {
+        let option = CString::new(option)?;
+        self.with_session(id, move |session| session.get_option_c(&option))
+    }
+
+    pub fn select_schema(&self, id: SessionId, schema_id: impl Into<Vec<u8>>) -> Result<()> {
+        let schema_id = CString::new(schema_id)?;
+        self.with_session(id, move |session| session.select_schema_c(&schema_id))
+    }
+}
+
+/// 候選和翻頁。
+impl RimeHandle {
+    pub fn select_candidate_on_current_page(&self, id: SessionId, index: usize) -> Result<()> {
+        self.with_session(id, move |session| {
+            session.select_candidate_on_current_page(index)
+        })
+    }
+
+    pub fn highlight_candidate_on_current_page(&self, id: SessionId, index: usize) -> Result<()> {
+        self.with_session(id, move |session| {
+            session.highlight_candidate_on_current_page(index)
+        })
+    }
+
+    pub fn delete_candidate_on_current_page(&self, id: SessionId, index: usize) -> Result<()> {
+        self.with_session(id, move |session| {
+            session.delete_candidate_on_current_page(index)
+        })
+    }
+
+    /// 前後翻頁，返回是否翻頁。
+    pub fn change_page(&self, id: SessionId, backward: bool) -> Result<bool> {
+        self.with_session(id, move |session| session.change_page(backward))
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Candidate, Commit, Composition, Context, Menu, Result, Status};
+
+/// 組合快照，不借用 `Rime`, 可跨線程傳遞。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompositionSnapshot {
+    pub length: i32,
+    pub cursor_pos: i32,
+    pub sel_start: i32,
+    pub sel_end: i32,
+    pub preedit: Option<String>,
+}
+
+/// 候選詞快照。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CandidateSnapshot {
+    pub text: String,
+    pub comment: Option<String>,
+}
+
+/// 候選菜單快照。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MenuSnapshot {
+    pub page_size: i32,
+    pub page_no: i32,
+    pub is_last_page: bool,
+    pub highlighted_candidate_index: i32,
+    pub candidates: Vec<CandidateSnapshot>,
+    pub select_keys: Option<String>,
+}
+
+/// 上下文快照。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContextSnapshot {
+    pub composition: CompositionSnapshot,
+    pub menu: MenuSnapshot,
+    pub commit_text_preview: Option<String>,
+    /// 未配置 `menu/alternative_select_labels` 時爲空。
+    pub select_labels: Vec<String>,
+}
+
+/// 狀態快照。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatusSnapshot {
+    pub schema_id: Option<String>,
+    pub schema_name: Option<String>,
+    pub is_disabled: bool,
+    pub is_composing: bool,
+    pub is_ascii_mode: bool,
+    pub is_full_shape: bool,
+    pub is_simplified: bool,
+    pub is_traditional: bool,
+    pub is_ascii_punct: bool,
+}
+
+/// 提交快照。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommitSnapshot {
+    pub text: Option<String>,
+}
+
+fn to_owned(s: Option<&str>) -> Option<String> {
+    s.map(str::to_string)
+}
+
+impl<'a> Composition<'a> {
+    pub fn snapshot(&self) -> Result<CompositionSnapshot> {
+        Ok(CompositionSnapshot {
+            length: self.length(),
+            cursor_pos: self.cursor_pos(),
+            sel_start: self.sel_start(),
+            sel_end: self.sel_end(),
+            preedit: to_owned(self.preedit()?),
+        })
+    }
+}
+
+impl<'a> Candidate<'a> {
+    pub fn snapshot(&self) -> Result<CandidateSnapshot> {
+        Ok(CandidateSnapshot {
+            text: self.text()?.unwrap_or_default().to_string(),
+            comment: to_owned(self.comment()?),
+        })
+    }
+}
+
+impl<'a> Menu<'a> {
+    pub fn snapshot(&self) -> Result<MenuSnapshot> {
+        let candidates = self
+            .candidates()
+            .flatten()
+            .map(|candidate| candidate.snapshot())
+            .collect::<Result<_>>()?;
+        Ok(MenuSnapshot {
+            page_size: self.page_size(),
+            page_no: self.page_no(),
+            is_last_page: self.is_last_page(),
+            highlighted_candidate_index: self.highlighted_candidate_index(),
+            candidates,
+            select_keys: to_owned(self.select_keys()?),
+        })
+    }
+}
+
+impl<'a> Context<'a> {
+    pub fn snapshot(&self) -> Result<ContextSnapshot> {
+        let select_labels = self
+            .select_labels()
+            .map(|label| label.map(|label| label.unwrap_or_default().to_string()))
+            .collect::<Result<_>>()?;
+        Ok(ContextSnapshot {
+            composition: self.composition().snapshot()?,
+            menu: self.menu().snapshot()?,
+            commit_text_preview: to_owned(self.commit_text_preview()?),
+            select_labels,
+        })
+    }
+}
+
+impl<'a> Status<'a> {
+    pub fn snapshot(&self) -> Result<StatusSnapshot> {
+        Ok(StatusSnapshot {
+            schema_id: to_owned(self.schema_id()?),
+            schema_name: to_owned(self.schema_name()?),
+            is_disabled: self.is_disabled(),
+            is_composing: self.is_composing(),
+            is_ascii_mode: self.is_ascii_mode(),
+            is_full_shape: self.is_full_shape(),
+            is_simplified: self.is_simplified(),
+            is_traditional: self.is_traditional(),
+            is_ascii_punct: self.is_ascii_punct(),
+        })
+    }
+}
+
+impl<'a> Commit<'a> {
+    pub fn snapshot(&self) -> Result<CommitSnapshot> {
+        Ok(CommitSnapshot {
+            text: to_owned(self.text()?),
+        })
+    }
+}
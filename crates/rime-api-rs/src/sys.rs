@@ -0,0 +1,460 @@
+//! librime 的 C 類型。
+//!
+//! 啓用 `librime` 時即 `librime_sys`; 否則爲按 `rime_api.h` 手寫的同佈局定義，
+//! 僅供 [`mock`](crate::MockDictionary) 後端使用，無需安裝 librime.
+
+#[cfg(feature = "librime")]
+pub use librime_sys::*;
+
+#[cfg(not(feature = "librime"))]
+pub use self::bindings::*;
+
+#[cfg(not(feature = "librime"))]
+#[allow(non_camel_case_types, non_upper_case_globals)]
+mod bindings {
+    use std::ffi::{c_char, c_int, c_uint, c_void};
+
+    pub type Bool = c_int;
+    pub type RimeSessionId = usize;
+
+    /// 以零初始化 `data_size` 結構並設置大小。
+    macro_rules! rime_struct {
+        ($var:ident : $type:ty) => {
+            let mut $var: $type = unsafe { std::mem::zeroed() };
+            $var.data_size =
+                (std::mem::size_of::<$type>() - std::mem::size_of_val(&$var.data_size)) as i32;
+        };
+    }
+    pub(crate) use rime_struct;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct rime_traits_t {
+        pub data_size: c_int,
+        pub shared_data_dir: *const c_char,
+        pub user_data_dir: *const c_char,
+        pub distribution_name: *const c_char,
+        pub distribution_code_name: *const c_char,
+        pub distribution_version: *const c_char,
+        pub app_name: *const c_char,
+        pub modules: *mut *const c_char,
+        pub min_log_level: c_int,
+        pub log_dir: *const c_char,
+        pub prebuilt_data_dir: *const c_char,
+        pub staging_dir: *const c_char,
+    }
+    pub type RimeTraits = rime_traits_t;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeComposition {
+        pub length: c_int,
+        pub cursor_pos: c_int,
+        pub sel_start: c_int,
+        pub sel_end: c_int,
+        pub preedit: *mut c_char,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeCandidate {
+        pub text: *mut c_char,
+        pub comment: *mut c_char,
+        pub reserved: *mut c_void,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeMenu {
+        pub page_size: c_int,
+        pub page_no: c_int,
+        pub is_last_page: Bool,
+        pub highlighted_candidate_index: c_int,
+        pub num_candidates: c_int,
+        pub candidates: *mut RimeCandidate,
+        pub select_keys: *mut c_char,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct rime_commit_t {
+        pub data_size: c_int,
+        pub text: *mut c_char,
+    }
+    pub type RimeCommit = rime_commit_t;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct rime_context_t {
+        pub data_size: c_int,
+        pub composition: RimeComposition,
+        pub menu: RimeMenu,
+        pub commit_text_preview: *mut c_char,
+        pub select_labels: *mut *mut c_char,
+    }
+    pub type RimeContext = rime_context_t;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct rime_status_t {
+        pub data_size: c_int,
+        pub schema_id: *mut c_char,
+        pub schema_name: *mut c_char,
+        pub is_disabled: Bool,
+        pub is_composing: Bool,
+        pub is_ascii_mode: Bool,
+        pub is_full_shape: Bool,
+        pub is_simplified: Bool,
+        pub is_traditional: Bool,
+        pub is_ascii_punct: Bool,
+    }
+    pub type RimeStatus = rime_status_t;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeCandidateListIterator {
+        pub ptr: *mut c_void,
+        pub index: c_int,
+        pub candidate: RimeCandidate,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeConfig {
+        pub ptr: *mut c_void,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeConfigIterator {
+        pub list: *mut c_void,
+        pub map: *mut c_void,
+        pub index: c_int,
+        pub key: *const c_char,
+        pub path: *const c_char,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeSchemaListItem {
+        pub schema_id: *mut c_char,
+        pub name: *mut c_char,
+        pub reserved: *mut c_void,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeSchemaList {
+        pub size: usize,
+        pub list: *mut RimeSchemaListItem,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeStringSlice {
+        pub str_: *const c_char,
+        pub length: usize,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeCustomApi {
+        pub data_size: c_int,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RimeModule {
+        pub data_size: c_int,
+        pub module_name: *const c_char,
+        pub initialize: Option<unsafe extern "C" fn()>,
+        pub finalize: Option<unsafe extern "C" fn()>,
+        pub get_api: Option<unsafe extern "C" fn() -> *mut RimeCustomApi>,
+    }
+
+    pub type RimeNotificationHandler = Option<
+        unsafe extern "C" fn(
+            context_object: *mut c_void,
+            session_id: RimeSessionId,
+            message_type: *const c_char,
+            message_value: *const c_char,
+        ),
+    >;
+
+    /// 模擬後端用到的鍵碼，同 X11 keysym.
+    pub type RimeKeyCode = c_uint;
+    pub const RimeKeyCode_XK_space: RimeKeyCode = 0x0020;
+    pub const RimeKeyCode_XK_minus: RimeKeyCode = 0x002d;
+    pub const RimeKeyCode_XK_equal: RimeKeyCode = 0x003d;
+    pub const RimeKeyCode_XK_BackSpace: RimeKeyCode = 0xff08;
+    pub const RimeKeyCode_XK_Return: RimeKeyCode = 0xff0d;
+    pub const RimeKeyCode_XK_Escape: RimeKeyCode = 0xff1b;
+    pub const RimeKeyCode_XK_Up: RimeKeyCode = 0xff52;
+    pub const RimeKeyCode_XK_Down: RimeKeyCode = 0xff54;
+    pub const RimeKeyCode_XK_Page_Up: RimeKeyCode = 0xff55;
+    pub const RimeKeyCode_XK_Page_Down: RimeKeyCode = 0xff56;
+
+    pub type RimeModifier = c_uint;
+    pub const RimeModifier_kShiftMask: RimeModifier = 1 << 0;
+    pub const RimeModifier_kLockMask: RimeModifier = 1 << 1;
+    pub const RimeModifier_kControlMask: RimeModifier = 1 << 2;
+    pub const RimeModifier_kAltMask: RimeModifier = 1 << 3;
+    pub const RimeModifier_kSuperMask: RimeModifier = 1 << 26;
+    pub const RimeModifier_kReleaseMask: RimeModifier = 1 << 30;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct rime_api_t {
+        pub data_size: c_int,
+        pub setup: Option<unsafe extern "C" fn(traits: *mut RimeTraits)>,
+        pub set_notification_handler: Option<
+            unsafe extern "C" fn(handler: RimeNotificationHandler, context_object: *mut c_void),
+        >,
+        pub initialize: Option<unsafe extern "C" fn(traits: *mut RimeTraits)>,
+        pub finalize: Option<unsafe extern "C" fn()>,
+        pub start_maintenance: Option<unsafe extern "C" fn(full_check: Bool) -> Bool>,
+        pub is_maintenance_mode: Option<unsafe extern "C" fn() -> Bool>,
+        pub join_maintenance_thread: Option<unsafe extern "C" fn()>,
+        pub deployer_initialize: Option<unsafe extern "C" fn(traits: *mut RimeTraits)>,
+        pub prebuild: Option<unsafe extern "C" fn() -> Bool>,
+        pub deploy: Option<unsafe extern "C" fn() -> Bool>,
+        pub deploy_schema: Option<unsafe extern "C" fn(schema_file: *const c_char) -> Bool>,
+        pub deploy_config_file: Option<
+            unsafe extern "C" fn(file_name: *const c_char, version_key: *const c_char) -> Bool,
+        >,
+        pub sync_user_data: Option<unsafe extern "C" fn() -> Bool>,
+        pub create_session: Option<unsafe extern "C" fn() -> RimeSessionId>,
+        pub find_session: Option<unsafe extern "C" fn(session_id: RimeSessionId) -> Bool>,
+        pub destroy_session: Option<unsafe extern "C" fn(session_id: RimeSessionId) -> Bool>,
+        pub cleanup_stale_sessions: Option<unsafe extern "C" fn()>,
+        pub cleanup_all_sessions: Option<unsafe extern "C" fn()>,
+        pub process_key: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, keycode: c_int, mask: c_int) -> Bool,
+        >,
+        pub commit_composition: Option<unsafe extern "C" fn(session_id: RimeSessionId) -> Bool>,
+        pub clear_composition: Option<unsafe extern "C" fn(session_id: RimeSessionId)>,
+        pub get_commit: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, commit: *mut RimeCommit) -> Bool,
+        >,
+        pub free_commit: Option<unsafe extern "C" fn(commit: *mut RimeCommit) -> Bool>,
+        pub get_context: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, context: *mut RimeContext) -> Bool,
+        >,
+        pub free_context: Option<unsafe extern "C" fn(ctx: *mut RimeContext) -> Bool>,
+        pub get_status: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, status: *mut RimeStatus) -> Bool,
+        >,
+        pub free_status: Option<unsafe extern "C" fn(status: *mut RimeStatus) -> Bool>,
+        pub set_option: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, option: *const c_char, value: Bool),
+        >,
+        pub get_option:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, option: *const c_char) -> Bool>,
+        pub set_property: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                prop: *const c_char,
+                value: *const c_char,
+            ),
+        >,
+        pub get_property: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                prop: *const c_char,
+                value: *mut c_char,
+                buffer_size: usize,
+            ) -> Bool,
+        >,
+        pub get_schema_list: Option<unsafe extern "C" fn(schema_list: *mut RimeSchemaList) -> Bool>,
+        pub free_schema_list: Option<unsafe extern "C" fn(schema_list: *mut RimeSchemaList)>,
+        pub get_current_schema: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                schema_id: *mut c_char,
+                buffer_size: usize,
+            ) -> Bool,
+        >,
+        pub select_schema: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, schema_id: *const c_char) -> Bool,
+        >,
+        pub schema_open:
+            Option<unsafe extern "C" fn(schema_id: *const c_char, config: *mut RimeConfig) -> Bool>,
+        pub config_open:
+            Option<unsafe extern "C" fn(config_id: *const c_char, config: *mut RimeConfig) -> Bool>,
+        pub config_close: Option<unsafe extern "C" fn(config: *mut RimeConfig) -> Bool>,
+        pub config_get_bool: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *mut Bool,
+            ) -> Bool,
+        >,
+        pub config_get_int: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *mut c_int,
+            ) -> Bool,
+        >,
+        pub config_get_double: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *mut f64,
+            ) -> Bool,
+        >,
+        pub config_get_string: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *mut c_char,
+                buffer_size: usize,
+            ) -> Bool,
+        >,
+        pub config_get_cstring: Option<
+            unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char) -> *const c_char,
+        >,
+        pub config_update_signature:
+            Option<unsafe extern "C" fn(config: *mut RimeConfig, signer: *const c_char) -> Bool>,
+        pub config_begin_map: Option<
+            unsafe extern "C" fn(
+                iterator: *mut RimeConfigIterator,
+                config: *mut RimeConfig,
+                key: *const c_char,
+            ) -> Bool,
+        >,
+        pub config_next: Option<unsafe extern "C" fn(iterator: *mut RimeConfigIterator) -> Bool>,
+        pub config_end: Option<unsafe extern "C" fn(iterator: *mut RimeConfigIterator)>,
+        pub simulate_key_sequence: Option<
+            unsafe extern "C" fn(session_id: RimeSessionId, key_sequence: *const c_char) -> Bool,
+        >,
+        pub register_module: Option<unsafe extern "C" fn(module: *mut RimeModule) -> Bool>,
+        pub find_module:
+            Option<unsafe extern "C" fn(module_name: *const c_char) -> *mut RimeModule>,
+        pub run_task: Option<unsafe extern "C" fn(task_name: *const c_char) -> Bool>,
+        pub get_shared_data_dir: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub get_user_data_dir: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub get_sync_dir: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub get_user_id: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub get_user_data_sync_dir:
+            Option<unsafe extern "C" fn(dir: *mut c_char, buffer_size: usize)>,
+        pub config_init: Option<unsafe extern "C" fn(config: *mut RimeConfig) -> Bool>,
+        pub config_load_string:
+            Option<unsafe extern "C" fn(config: *mut RimeConfig, yaml: *const c_char) -> Bool>,
+        pub config_set_bool: Option<
+            unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char, value: Bool) -> Bool,
+        >,
+        pub config_set_int: Option<
+            unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char, value: c_int) -> Bool,
+        >,
+        pub config_set_double: Option<
+            unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char, value: f64) -> Bool,
+        >,
+        pub config_set_string: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *const c_char,
+            ) -> Bool,
+        >,
+        pub config_get_item: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *mut RimeConfig,
+            ) -> Bool,
+        >,
+        pub config_set_item: Option<
+            unsafe extern "C" fn(
+                config: *mut RimeConfig,
+                key: *const c_char,
+                value: *mut RimeConfig,
+            ) -> Bool,
+        >,
+        pub config_clear:
+            Option<unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char) -> Bool>,
+        pub config_create_list:
+            Option<unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char) -> Bool>,
+        pub config_create_map:
+            Option<unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char) -> Bool>,
+        pub config_list_size:
+            Option<unsafe extern "C" fn(config: *mut RimeConfig, key: *const c_char) -> usize>,
+        pub config_begin_list: Option<
+            unsafe extern "C" fn(
+                iterator: *mut RimeConfigIterator,
+                config: *mut RimeConfig,
+                key: *const c_char,
+            ) -> Bool,
+        >,
+        pub get_input: Option<unsafe extern "C" fn(session_id: RimeSessionId) -> *const c_char>,
+        pub get_caret_pos: Option<unsafe extern "C" fn(session_id: RimeSessionId) -> usize>,
+        pub select_candidate:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, index: usize) -> Bool>,
+        pub get_version: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub set_caret_pos:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, caret_pos: usize)>,
+        pub select_candidate_on_current_page:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, index: usize) -> Bool>,
+        pub candidate_list_begin: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                iterator: *mut RimeCandidateListIterator,
+            ) -> Bool,
+        >,
+        pub candidate_list_next:
+            Option<unsafe extern "C" fn(iterator: *mut RimeCandidateListIterator) -> Bool>,
+        pub candidate_list_end:
+            Option<unsafe extern "C" fn(iterator: *mut RimeCandidateListIterator)>,
+        pub user_config_open:
+            Option<unsafe extern "C" fn(config_id: *const c_char, config: *mut RimeConfig) -> Bool>,
+        pub candidate_list_from_index: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                iterator: *mut RimeCandidateListIterator,
+                index: c_int,
+            ) -> Bool,
+        >,
+        pub get_prebuilt_data_dir: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub get_staging_dir: Option<unsafe extern "C" fn() -> *const c_char>,
+        pub commit_proto:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, builder: *mut c_void)>,
+        pub context_proto:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, builder: *mut c_void)>,
+        pub status_proto:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, builder: *mut c_void)>,
+        pub get_state_label: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                option_name: *const c_char,
+                state: Bool,
+            ) -> *const c_char,
+        >,
+        pub delete_candidate:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, index: usize) -> Bool>,
+        pub delete_candidate_on_current_page:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, index: usize) -> Bool>,
+        pub get_state_label_abbreviated: Option<
+            unsafe extern "C" fn(
+                session_id: RimeSessionId,
+                option_name: *const c_char,
+                state: Bool,
+                abbreviated: Bool,
+            ) -> RimeStringSlice,
+        >,
+        pub set_input:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, input: *const c_char) -> Bool>,
+        pub get_shared_data_dir_s:
+            Option<unsafe extern "C" fn(dir: *mut c_char, buffer_size: usize)>,
+        pub get_user_data_dir_s: Option<unsafe extern "C" fn(dir: *mut c_char, buffer_size: usize)>,
+        pub get_prebuilt_data_dir_s:
+            Option<unsafe extern "C" fn(dir: *mut c_char, buffer_size: usize)>,
+        pub get_staging_dir_s: Option<unsafe extern "C" fn(dir: *mut c_char, buffer_size: usize)>,
+        pub get_sync_dir_s: Option<unsafe extern "C" fn(dir: *mut c_char, buffer_size: usize)>,
+        pub highlight_candidate:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, index: usize) -> Bool>,
+        pub highlight_candidate_on_current_page:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, index: usize) -> Bool>,
+        pub change_page:
+            Option<unsafe extern "C" fn(session_id: RimeSessionId, backward: Bool) -> Bool>,
+    }
+}
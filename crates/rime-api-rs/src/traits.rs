@@ -1,7 +1,9 @@
-use std::ffi::{CString, NulError};
+use std::ffi::CString;
 
 use bon::bon;
-use librime_sys::{rime_struct, rime_traits_t};
+
+use crate::sys::{rime_struct, rime_traits_t};
+use crate::Result;
 
 pub struct Traits {
     raw: rime_traits_t,
@@ -24,7 +26,7 @@ impl Traits {
         log_dir: Option<&str>,
         prebuilt_data_dir: Option<&str>,
         staging_dir: Option<&str>,
-    ) -> Result<Self, NulError> {
+    ) -> Result<Self> {
         // initialize
         rime_struct!(raw: rime_traits_t);
         let mut resources = Vec::new();
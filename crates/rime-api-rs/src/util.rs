@@ -1,32 +1,81 @@
 /// Rime api call
+///
+/// 按 `data_size` 檢查函數是否存在，返回 `Result`.
 #[macro_export]
 macro_rules! rime_api_call {
     ($api:expr, $f:ident $(,$args:expr)*) => {
-        unsafe {
-            (*$api.as_ref()).$f.unwrap()($($args),*)
+        match $crate::rime_api_fn!($api, $f) {
+            Some(f) => {
+                // 參數均爲指針或整數，由調用處構造
+                #[allow(clippy::macro_metavars_in_unsafe)]
+                let value = unsafe { f($($args),*) };
+                Ok(value)
+            }
+            None => Err($crate::Error::MissingApiFunction(stringify!($f))),
         }
     };
 }
 
+/// 獲取 api 函數指針。
+///
+/// 舊版 librime 的 `rime_api_t` 較短，超出 `data_size` 的字段視爲不存在。
+#[macro_export]
+macro_rules! rime_api_fn {
+    ($api:expr, $f:ident) => {{
+        let api: std::ptr::NonNull<$crate::sys::rime_api_t> = $api;
+        let api = unsafe { api.as_ref() };
+        let end =
+            std::mem::offset_of!($crate::sys::rime_api_t, $f) + std::mem::size_of_val(&api.$f);
+        let size = api.data_size as usize + std::mem::size_of_val(&api.data_size);
+        if end <= size {
+            api.$f
+        } else {
+            None
+        }
+    }};
+}
+
+/// 調用返回 `Bool` 的 api, `False` 視爲失敗。
+#[macro_export]
+macro_rules! rime_api_try {
+    ($api:expr, $f:ident $(,$args:expr)*) => {
+        $crate::rime_api_call!($api, $f $(,$args)*).and_then(|ok| {
+            if ok != 0 {
+                Ok(())
+            } else {
+                Err($crate::Error::OperationFailed(stringify!($f)))
+            }
+        })
+    };
+}
+
+/// 調用返回 `Bool` 的 api, 轉換爲 `bool`.
+#[macro_export]
+macro_rules! rime_api_bool {
+    ($api:expr, $f:ident $(,$args:expr)*) => {
+        $crate::rime_api_call!($api, $f $(,$args)*).map(|value| value != 0)
+    };
+}
+
 #[macro_export]
 macro_rules! struct_impl_managed {
     ($name:ident $(,$drop_fn:ident)?) => {
         paste::paste! {
             pub struct $name<'a> {
-                api: &'a crate::Rime,
-                raw: librime_sys::[<Rime $name>],
+                api: &'a $crate::Rime,
+                raw: $crate::sys::[<Rime $name>],
             }
 
             impl<'a> $name<'a> {
-                pub fn from_raw(api: &'a super::Rime, raw: librime_sys::[<Rime $name>]) -> Self {
+                pub fn from_raw(api: &'a $crate::Rime, raw: $crate::sys::[<Rime $name>]) -> Self {
                     Self { api, raw }
                 }
 
-                pub fn raw(&self) -> &librime_sys::[<Rime $name>] {
+                pub fn raw(&self) -> &$crate::sys::[<Rime $name>] {
                     &self.raw
                 }
 
-                pub fn raw_mut(&mut self) -> &mut librime_sys::[<Rime $name>] {
+                pub fn raw_mut(&mut self) -> &mut $crate::sys::[<Rime $name>] {
                     &mut self.raw
                 }
             }
@@ -37,7 +86,7 @@ macro_rules! struct_impl_managed {
     (@impl_drop $name:ident $drop_fn:ident) => {
         impl<'a> Drop for $name<'a> {
             fn drop(&mut self) {
-                crate::rime_api_call!(self.api.raw(), $drop_fn, self.raw_mut());
+                let _ = $crate::rime_api_call!(self.api.raw(), $drop_fn, self.raw_mut());
             }
         }
     };
@@ -46,7 +95,11 @@ macro_rules! struct_impl_managed {
         paste::paste! {
             impl<'a> Drop for $name<'a> {
                 fn drop(&mut self) {
-                    crate::rime_api_call!(self.api.raw(), [<free_ $name:snake>], self.raw_mut());
+                    let _ = $crate::rime_api_call!(
+                        self.api.raw(),
+                        [<free_ $name:snake>],
+                        self.raw_mut()
+                    );
                 }
             }
         }
@@ -58,15 +111,15 @@ macro_rules! struct_impl_reference {
     ($name:ident) => {
         paste::paste! {
             pub struct $name<'a> {
-                raw: &'a librime_sys::[<Rime $name>],
+                raw: &'a $crate::sys::[<Rime $name>],
             }
 
             impl<'a> $name<'a> {
-                pub fn from_raw(raw: &'a librime_sys::[<Rime $name>]) -> Self {
+                pub fn from_raw(raw: &'a $crate::sys::[<Rime $name>]) -> Self {
                     Self { raw }
                 }
 
-                pub fn raw(&self) -> &librime_sys::[<Rime $name>] {
+                pub fn raw(&self) -> &$crate::sys::[<Rime $name>] {
                     self.raw
                 }
             }
@@ -98,12 +151,12 @@ macro_rules! impl_getters {
             $(#[$attr])*
             pub fn [<$field _c>](&self) -> Option<&std::ffi::CStr> {
                 let ptr = self.raw.$field;
-                crate::ptr_to_cstr!(ptr)
+                $crate::ptr_to_cstr!(ptr)
             }
 
             $(#[$attr])*
-            pub fn $field(&self) -> Option<Result<&str, std::str::Utf8Error>> {
-                self.[<$field _c>]().map(std::ffi::CStr::to_str)
+            pub fn $field(&self) -> $crate::Result<Option<&str>> {
+                $crate::cstr_to_str!(self.[<$field _c>]())
             }
         }
     };
@@ -132,7 +185,19 @@ macro_rules! impl_getters {
 
 #[macro_export]
 macro_rules! ptr_to_cstr {
-    ($ptr:expr) => {
-        (!$ptr.is_null()).then(|| unsafe { std::ffi::CStr::from_ptr($ptr) })
+    ($ptr:expr) => {{
+        let ptr = $ptr;
+        (!ptr.is_null()).then(|| unsafe { std::ffi::CStr::from_ptr(ptr) })
+    }};
+}
+
+/// 將 `Option<&CStr>` 轉換爲 `Result<Option<&str>>`.
+#[macro_export]
+macro_rules! cstr_to_str {
+    ($cstr:expr) => {
+        $cstr
+            .map(std::ffi::CStr::to_str)
+            .transpose()
+            .map_err($crate::Error::from)
     };
 }
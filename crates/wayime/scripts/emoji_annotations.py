@@ -0,0 +1,156 @@
+#!/usr/bin/env python3
+"""Regenerate src/emoji/annotations.tsv from Unicode and CLDR data.
+
+Usage: scripts/emoji_annotations.py [--emoji-test FILE] [--cldr DIR | --no-cldr] \
+           > src/emoji/annotations.tsv
+
+Emoji come from emoji-test.txt (fully-qualified, without skin-tone variants)
+in its order, followed by every other character that CLDR annotates, such as
+arrows, math and currency symbols, in codepoint order. Each line has an
+English and a Chinese column. English keywords come from the CLDR en
+annotations and derived annotations, plus the hand-picked keywords in
+src/emoji/keywords.tsv, whose symbols are added when CLDR lacks them. Chinese
+keywords come from the CLDR zh and zh_Hant annotations, plus
+src/emoji/keywords_zh.tsv.
+
+Both sources are downloaded unless given as local paths; DIR is the `common`
+directory of a CLDR checkout. With --no-cldr the keywords are only the names
+and the two keyword files. The picker matches the English column against the
+typed query and the Chinese column against the Rime candidates for it.
+"""
+
+import argparse
+import os
+import sys
+import urllib.request
+import xml.etree.ElementTree as ET
+
+EMOJI_TEST = "https://unicode.org/Public/emoji/latest/emoji-test.txt"
+CLDR = "https://raw.githubusercontent.com/unicode-org/cldr/main/common"
+DATA = os.path.join(os.path.dirname(__file__), "..", "src", "emoji")
+KEYWORDS = os.path.join(DATA, "keywords.tsv")
+KEYWORDS_ZH = os.path.join(DATA, "keywords_zh.tsv")
+SKIN_TONES = {chr(c) for c in range(0x1F3FB, 0x1F400)}
+
+
+def read(source):
+    if "://" in source:
+        with urllib.request.urlopen(source) as response:
+            return response.read().decode("utf-8")
+    with open(source, encoding="utf-8") as file:
+        return file.read()
+
+
+def emoji_test(text):
+    """(emoji, name) in emoji-test.txt order."""
+    for line in text.splitlines():
+        if line.startswith("#") or "; fully-qualified" not in line:
+            continue
+        # 1F600 ; fully-qualified # 😀 E1.0 grinning face
+        _, comment = line.split("#", 1)
+        emoji, _version, name = comment.strip().split(" ", 2)
+        if SKIN_TONES.isdisjoint(emoji):
+            yield emoji, name
+
+
+def version(text):
+    for line in text.splitlines():
+        if line.startswith("# Version:"):
+            return line.split(":", 1)[1].strip()
+    return "?"
+
+
+def annotations(cldr, *locales):
+    """char -> (name, keywords), CLDR strips U+FE0F from `cp`.
+
+    Later locales add their keywords, the name comes from the first.
+    """
+    result = {}
+    for locale in locales:
+        for kind in ["annotations", "annotationsDerived"]:
+            root = ET.fromstring(read(f"{cldr}/{kind}/{locale}.xml"))
+            for node in root.iter("annotation"):
+                cp = node.get("cp")
+                name, keywords = result.get(cp, ("", []))
+                if node.get("type") == "tts":
+                    name = name or node.text.strip()
+                else:
+                    keywords = keywords + [k.strip() for k in node.text.split("|")]
+                result[cp] = (name, keywords)
+    return result
+
+
+def extra_keywords(path):
+    """char without U+FE0F -> (char, keywords) from a keyword file, in file order."""
+    result = {}
+    for line in read(path).splitlines():
+        if not line or line.startswith("# "):
+            continue
+        char, words = line.split("\t")
+        result[char.replace("\ufe0f", "")] = (char, words.split("|"))
+    return result
+
+
+def keywords(name, *words):
+    seen = []
+    for word in [name, *(w for group in words for w in group)]:
+        word = word.replace("\t", " ").replace("|", "/")
+        if word and word not in seen:
+            seen.append(word)
+    return "|".join(seen)
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--emoji-test", default=EMOJI_TEST)
+    cldr = parser.add_mutually_exclusive_group()
+    cldr.add_argument("--cldr", default=CLDR)
+    cldr.add_argument("--no-cldr", action="store_true")
+    args = parser.parse_args()
+
+    test = read(args.emoji_test)
+    en = {} if args.no_cldr else annotations(args.cldr, "en")
+    zh = {} if args.no_cldr else annotations(args.cldr, "zh", "zh_Hant")
+    extra = extra_keywords(KEYWORDS)
+    extra_zh = extra_keywords(KEYWORDS_ZH)
+    sources = f"emoji-test.txt {version(test)}"
+    if not args.no_cldr:
+        sources += ", CLDR en/zh/zh_Hant 註釋"
+    out = sys.stdout
+
+    def chinese(key):
+        zh_name, zh_words = zh.get(key, ("", []))
+        _, extra_words = extra_zh.get(key, (key, []))
+        if not zh_name and not extra_words:
+            return ""
+        return keywords(zh_name or extra_words[0], zh_words, extra_words)
+
+    out.write("# 表情\t英文名|英文關鍵詞\t中文名|中文關鍵詞\n")
+    out.write(
+        f"# 由 scripts/emoji_annotations.py 從 {sources}, keywords.tsv 和 keywords_zh.tsv 生成\n"
+    )
+    seen = set()
+    for emoji, name in emoji_test(test):
+        key = emoji.replace("\ufe0f", "")
+        seen.add(key)
+        en_name, en_words = en.get(key, (name, []))
+        _, extra_words = extra.get(key, (emoji, []))
+        english = keywords(en_name or name, en_words, extra_words)
+        out.write(f"{emoji}\t{english}\t{chinese(key)}\n")
+    # 其餘有註釋的符號
+    for cp in sorted(set(en) - seen, key=lambda cp: [ord(c) for c in cp]):
+        en_name, en_words = en[cp]
+        if not en_name:
+            continue
+        seen.add(cp)
+        _, extra_words = extra.get(cp, (cp, []))
+        english = keywords(en_name, en_words, extra_words)
+        out.write(f"{cp}\t{english}\t{chinese(cp)}\n")
+    # 手選的其他符號
+    for key, (char, words) in extra.items():
+        if key not in seen:
+            out.write(f"{char}\t{keywords(words[0], words[1:])}\t{chinese(key)}\n")
+
+
+if __name__ == "__main__":
+    main()
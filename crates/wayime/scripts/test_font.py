@@ -0,0 +1,118 @@
+#!/usr/bin/env python3
+"""Generate src/im/tests/box.ttf, the font used by the popup tests.
+
+Usage: scripts/test_font.py > src/im/tests/box.ttf
+
+The font has a single glyph, a filled box, as `.notdef` and no cmap, so every
+character is drawn as the same box with a 1 em advance. That is enough to
+exercise layout and rasterizing without depending on system fonts.
+"""
+
+import struct
+import sys
+
+UNITS_PER_EM = 1000
+ASCENDER = 800
+DESCENDER = -200
+# 方框的輪廓
+X_MIN, Y_MIN, X_MAX, Y_MAX = 100, 0, 900, 700
+
+
+def glyf():
+    points = [(X_MIN, Y_MIN), (X_MIN, Y_MAX), (X_MAX, Y_MAX), (X_MAX, Y_MIN)]
+    data = struct.pack(">hhhhh", 1, X_MIN, Y_MIN, X_MAX, Y_MAX)
+    data += struct.pack(">HH", len(points) - 1, 0)
+    # 均爲曲線上的點，坐標爲 16 位差值
+    data += bytes([0x01] * len(points))
+    for axis in range(2):
+        previous = 0
+        for point in points:
+            data += struct.pack(">h", point[axis] - previous)
+            previous = point[axis]
+    return data + b"\0" * (-len(data) % 4)
+
+
+def tables():
+    glyph = glyf()
+    head = struct.pack(
+        ">IIIIHHqqhhhhHHhhh",
+        0x00010000,  # version
+        0x00010000,  # fontRevision
+        0,  # checkSumAdjustment, 最後填入
+        0x5F0F3CF5,  # magicNumber
+        0x000B,  # flags
+        UNITS_PER_EM,
+        0,  # created
+        0,  # modified
+        X_MIN,
+        Y_MIN,
+        X_MAX,
+        Y_MAX,
+        0,  # macStyle
+        8,  # lowestRecPPEM
+        2,  # fontDirectionHint
+        0,  # indexToLocFormat, 短偏移
+        0,  # glyphDataFormat
+    )
+    hhea = struct.pack(
+        ">IhhhHhhhhhhhhhhhH",
+        0x00010000,
+        ASCENDER,
+        DESCENDER,
+        0,  # lineGap
+        UNITS_PER_EM,  # advanceWidthMax
+        X_MIN,  # minLeftSideBearing
+        UNITS_PER_EM - X_MAX,  # minRightSideBearing
+        X_MAX,  # xMaxExtent
+        1,  # caretSlopeRise
+        0,  # caretSlopeRun
+        0,  # caretOffset
+        0,
+        0,
+        0,
+        0,
+        0,  # metricDataFormat
+        1,  # numberOfHMetrics
+    )
+    maxp = struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, 1, 4, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0)
+    hmtx = struct.pack(">Hh", UNITS_PER_EM, X_MIN)
+    loca = struct.pack(">HH", 0, len(glyph) // 2)
+    return {
+        b"glyf": glyph,
+        b"head": head,
+        b"hhea": hhea,
+        b"hmtx": hmtx,
+        b"loca": loca,
+        b"maxp": maxp,
+    }
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def font():
+    entries = sorted(tables().items())
+    count = len(entries)
+    power = 1 << (count.bit_length() - 1)
+    header = struct.pack(
+        ">IHHHH", 0x00010000, count, power * 16, power.bit_length() - 1, (count - power) * 16
+    )
+    offset = len(header) + 16 * count
+    records = b""
+    body = b""
+    head_offset = 0
+    for tag, data in entries:
+        if tag == b"head":
+            head_offset = offset + len(body)
+        records += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    data = bytearray(header + records + body)
+    adjustment = (0xB1B0AFBA - checksum(bytes(data))) & 0xFFFFFFFF
+    struct.pack_into(">I", data, head_offset + 8, adjustment)
+    return bytes(data)
+
+
+if __name__ == "__main__":
+    sys.stdout.buffer.write(font())
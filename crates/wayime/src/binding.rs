@@ -0,0 +1,103 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+use xkbcommon::xkb::{self, Keysym, ModMask, KEYSYM_CASE_INSENSITIVE, KEYSYM_NO_FLAGS};
+
+// 標準 xkb 修飾鍵位
+pub const SHIFT: ModMask = 1 << 0;
+pub const LOCK: ModMask = 1 << 1;
+pub const CONTROL: ModMask = 1 << 2;
+pub const ALT: ModMask = 1 << 3;
+pub const SUPER: ModMask = 1 << 6;
+
+/// 綁定時只比較這些修飾鍵，忽略 Caps Lock 和 Num Lock.
+const BINDING_MODS: ModMask = SHIFT | CONTROL | ALT | SUPER;
+
+/// 快捷鍵，如 `Shift+Delete`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub mods: ModMask,
+    pub keysym: Keysym,
+}
+
+impl KeyBinding {
+    pub fn new(mods: ModMask, keysym: Keysym) -> Self {
+        Self { mods, keysym }
+    }
+
+    /// 按鍵和當前修飾鍵是否匹配。
+    pub fn matches(&self, keysym: Keysym, mods: ModMask) -> bool {
+        self.keysym == keysym && self.mods == mods & BINDING_MODS
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('+').map(str::trim).collect::<Vec<_>>();
+        let name = parts.pop().filter(|name| !name.is_empty());
+        let name = name.ok_or_else(|| format!("missing key in `{s}`"))?;
+        let mut mods = 0;
+        for part in parts {
+            mods |= match part.to_ascii_lowercase().as_str() {
+                "shift" => SHIFT,
+                "control" | "ctrl" => CONTROL,
+                "alt" | "mod1" => ALT,
+                "super" | "mod4" => SUPER,
+                _ => return Err(format!("unknown modifier `{part}` in `{s}`")),
+            };
+        }
+        let mut keysym = xkb::keysym_from_name(name, KEYSYM_NO_FLAGS);
+        if keysym == Keysym::NoSymbol {
+            keysym = xkb::keysym_from_name(name, KEYSYM_CASE_INSENSITIVE);
+        }
+        if keysym == Keysym::NoSymbol {
+            return Err(format!("unknown key `{name}` in `{s}`"));
+        }
+        Ok(Self { mods, keysym })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (mask, name) in [
+            (SHIFT, "Shift"),
+            (CONTROL, "Control"),
+            (ALT, "Alt"),
+            (SUPER, "Super"),
+        ] {
+            if self.mods & mask != 0 {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{}", xkb::keysym_get_name(self.keysym))
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let binding = "ctrl+Delete".parse::<KeyBinding>().unwrap();
+        assert_eq!(binding, KeyBinding::new(CONTROL, Keysym::Delete));
+        assert_eq!(binding.to_string(), "Control+Delete");
+        // 忽略 Caps Lock
+        assert!(binding.matches(Keysym::Delete, CONTROL | 2));
+        assert!(!binding.matches(Keysym::Delete, CONTROL | SHIFT));
+        assert!("Hyper+x".parse::<KeyBinding>().is_err());
+        assert!("Shift+".parse::<KeyBinding>().is_err());
+    }
+}
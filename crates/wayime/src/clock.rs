@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+/// 單調時鐘，測試時可替換。
+pub trait Clock {
+    /// 自任意固定起點經過的時間。
+    fn now(&self) -> Duration;
+}
+
+/// 基於 [`Instant`] 的系統單調時鐘。
+pub struct MonotonicClock {
+    origin: Instant,
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// 與合成器時間基準對齊的毫秒時間戳。
+///
+/// 合成器的時間戳起點未知，合成事件的時間取最近收到的時間戳加上此後經過的時間。
+pub struct EventTime {
+    clock: Box<dyn Clock>,
+    /// 最近收到的時間戳及收到時的時鐘讀數
+    base: Option<(u32, Duration)>,
+}
+
+impl EventTime {
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Box::new(clock),
+            base: None,
+        }
+    }
+
+    /// 記錄合成器事件的時間戳。
+    pub fn observe(&mut self, time: u32) {
+        self.base = Some((time, self.clock.now()));
+    }
+
+    /// 合成事件的時間戳，尚未收到時間戳時使用本地時鐘。
+    pub fn now(&self) -> u32 {
+        let now = self.clock.now();
+        match self.base {
+            Some((time, at)) => time.wrapping_add(now.saturating_sub(at).as_millis() as u32),
+            None => now.as_millis() as u32,
+        }
+    }
+}
+
+/// 手動推進的時鐘。
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct ManualClock(std::rc::Rc<std::cell::Cell<Duration>>);
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_compositor_time() {
+        let clock = ManualClock::default();
+        let mut time = EventTime::new(clock.clone());
+        clock.advance(Duration::from_millis(7));
+        assert_eq!(time.now(), 7);
+
+        time.observe(u32::MAX - 10);
+        clock.advance(Duration::from_millis(25));
+        // 時間戳按 u32 迴繞
+        assert_eq!(time.now(), 14);
+    }
+}
@@ -0,0 +1,135 @@
+use serde::Deserialize;
+use xkbcommon::xkb::Keysym;
+
+use crate::binding::{self, KeyBinding};
+
+/// Unicode 碼位的最大值
+const MAX: u32 = 0x10FFFF;
+
+/// 碼位輸入配置。
+///
+/// ```toml
+/// [codepoint]
+/// keys = ["Control+Shift+U"]
+/// prefix = "U+"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CodepointConfig {
+    /// 進入碼位輸入的快捷鍵
+    pub keys: Vec<KeyBinding>,
+    /// 組合中輸入碼加上按下的字符等於此前綴時進入，不區分大小寫
+    pub prefix: Option<String>,
+}
+
+impl Default for CodepointConfig {
+    fn default() -> Self {
+        Self {
+            keys: vec![KeyBinding::new(
+                binding::CONTROL | binding::SHIFT,
+                Keysym::U,
+            )],
+            prefix: Some("U+".to_string()),
+        }
+    }
+}
+
+/// 以十六進制數字輸入碼位，不經 Rime.
+#[derive(Debug, Default)]
+pub struct CodepointInput {
+    digits: String,
+}
+
+impl CodepointInput {
+    /// 追加一位十六進制數字，不是數字或超出碼位範圍時拒絕。
+    pub fn push(&mut self, digit: char) -> bool {
+        if !digit.is_ascii_hexdigit() || self.digits.len() >= 6 {
+            return false;
+        }
+        let value = (self.value().unwrap_or(0) << 4) | digit.to_digit(16).unwrap();
+        if value > MAX {
+            return false;
+        }
+        self.digits.push(digit.to_ascii_uppercase());
+        true
+    }
+
+    /// 刪除最後一位，已空時返回 `false`.
+    pub fn pop(&mut self) -> bool {
+        self.digits.pop().is_some()
+    }
+
+    fn value(&self) -> Option<u32> {
+        u32::from_str_radix(&self.digits, 16).ok()
+    }
+
+    /// 輸入的字符，代理碼位和 C0/C1 控制字符等無效時爲 `None`.
+    ///
+    /// NUL 無法通過 `commit_string` 發送，其他控制字符也不應作爲文本提交。
+    pub fn char(&self) -> Option<char> {
+        self.value()
+            .and_then(char::from_u32)
+            .filter(|c| !c.is_control())
+    }
+
+    /// 預編輯文本，如 `U+4E2D 中 CJK UNIFIED IDEOGRAPH-4E2D`.
+    pub fn preedit(&self) -> String {
+        let mut text = format!("U+{}", self.digits);
+        match self.char() {
+            Some(c) => {
+                text.push(' ');
+                text.push(c);
+                text.push(' ');
+                if let Some(name) = unicode_names2::name(c) {
+                    text.push_str(&name.to_string());
+                }
+            }
+            None if !self.digits.is_empty() => text.push_str(" ✗"),
+            None => {}
+        }
+        text.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(digits: &str) -> CodepointInput {
+        let mut input = CodepointInput::default();
+        for digit in digits.chars() {
+            input.push(digit);
+        }
+        input
+    }
+
+    #[test]
+    fn build_codepoint() {
+        let input = input("4e2d");
+        assert_eq!(input.char(), Some('中'));
+        assert_eq!(input.preedit(), "U+4E2D 中 CJK UNIFIED IDEOGRAPH-4E2D");
+        assert_eq!(CodepointInput::default().preedit(), "U+");
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let mut max = input("10FFFF");
+        assert!(!max.push('0'));
+        assert!(!max.push('g'));
+        assert_eq!(max.char(), Some('\u{10FFFF}'));
+        // 代理碼位
+        let surrogate = input("d800");
+        assert_eq!(surrogate.char(), None);
+        assert_eq!(surrogate.preedit(), "U+D800 ✗");
+        // 控制字符
+        let mut nul = CodepointInput::default();
+        assert!(nul.push('0'));
+        assert_eq!(nul.char(), None);
+        assert_eq!(nul.preedit(), "U+0 ✗");
+        assert_eq!(input("85").char(), None);
+        assert_eq!(input("a0").char(), Some('\u{A0}'));
+        let mut one = input("1");
+        assert!(one.pop());
+        assert!(!one.pop());
+    }
+}
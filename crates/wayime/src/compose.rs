@@ -0,0 +1,180 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+};
+
+use log::{info, warn};
+use xkbcommon::xkb::{
+    self,
+    compose::{self, FeedResult, Status},
+    Keysym,
+};
+
+/// 輸入一個按鍵後的組合狀態。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Composed {
+    /// 不屬於任何序列，按原樣處理
+    Nothing,
+    /// 序列進行中
+    Composing,
+    /// 序列完成，`keysym` 爲結果的單個 keysym, 如有
+    Done {
+        keysym: Option<Keysym>,
+        text: String,
+    },
+    /// 序列無效，已丟棄
+    Cancelled,
+}
+
+/// 按 locale 的 Compose 表組合死鍵和 Compose 序列。
+pub struct Composer {
+    state: compose::State,
+    /// 進行中的序列，用於在預編輯文本中顯示
+    sequence: String,
+}
+
+impl Composer {
+    /// 按 `LC_ALL`, `LC_CTYPE`, `LANG` 選取 Compose 表，沒有時返回 `None`.
+    pub fn from_env(context: &xkb::Context) -> Option<Self> {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .filter_map(env::var_os)
+            .find(|value| !value.is_empty())
+            .unwrap_or_else(|| OsString::from("C"));
+        Self::new(context, &locale)
+    }
+
+    pub fn new(context: &xkb::Context, locale: &OsStr) -> Option<Self> {
+        match compose::Table::new_from_locale(context, locale, compose::COMPILE_NO_FLAGS) {
+            Ok(table) => {
+                info!("Compose table for {locale:?}");
+                Some(Self {
+                    state: compose::State::new(&table, compose::STATE_NO_FLAGS),
+                    sequence: String::new(),
+                })
+            }
+            Err(()) => {
+                warn!("No compose table for {locale:?}, dead keys go to Rime");
+                None
+            }
+        }
+    }
+
+    /// 由 Compose 文件內容創建，用於測試。
+    #[cfg(test)]
+    pub fn from_buffer(context: &xkb::Context, buffer: &str) -> Option<Self> {
+        let table = compose::Table::new_from_buffer(
+            context,
+            buffer,
+            "C",
+            compose::FORMAT_TEXT_V1,
+            compose::COMPILE_NO_FLAGS,
+        )
+        .ok()?;
+        Some(Self {
+            state: compose::State::new(&table, compose::STATE_NO_FLAGS),
+            sequence: String::new(),
+        })
+    }
+
+    /// 輸入一個 keysym. 修飾鍵等被忽略的 keysym 返回 [`Composed::Nothing`].
+    pub fn feed(&mut self, keysym: Keysym) -> Composed {
+        if matches!(self.state.feed(keysym), FeedResult::Ignored) {
+            return Composed::Nothing;
+        }
+        match self.state.status() {
+            Status::Composing => {
+                self.sequence.push_str(&display(keysym));
+                Composed::Composing
+            }
+            Status::Composed => {
+                let composed = Composed::Done {
+                    keysym: self
+                        .state
+                        .keysym()
+                        .filter(|&keysym| keysym != Keysym::NoSymbol),
+                    text: self.state.utf8().unwrap_or_default(),
+                };
+                self.reset();
+                composed
+            }
+            Status::Cancelled => {
+                self.reset();
+                Composed::Cancelled
+            }
+            Status::Nothing => {
+                self.sequence.clear();
+                Composed::Nothing
+            }
+        }
+    }
+
+    /// 進行中的序列，如 `´` 或 `·'`.
+    pub fn sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    pub fn reset(&mut self) {
+        self.state.reset();
+        self.sequence.clear();
+    }
+}
+
+/// 序列中按鍵的顯示文本，死鍵顯示對應的空白字符。
+fn display(keysym: Keysym) -> String {
+    let text = match keysym {
+        Keysym::Multi_key => "·",
+        Keysym::dead_grave => "`",
+        Keysym::dead_acute => "´",
+        Keysym::dead_circumflex => "^",
+        Keysym::dead_tilde => "~",
+        Keysym::dead_macron => "¯",
+        Keysym::dead_breve => "˘",
+        Keysym::dead_abovedot => "˙",
+        Keysym::dead_diaeresis => "¨",
+        Keysym::dead_abovering => "˚",
+        Keysym::dead_doubleacute => "˝",
+        Keysym::dead_caron => "ˇ",
+        Keysym::dead_cedilla => "¸",
+        Keysym::dead_ogonek => "˛",
+        _ => {
+            return xkb::keysym_to_utf8(keysym)
+                .trim_end_matches('\0')
+                .to_string()
+        }
+    };
+    text.to_string()
+}
+
+/// 測試用的 Compose 序列。
+#[cfg(test)]
+pub const TEST_COMPOSE: &str = r#"
+<dead_acute> <e> : "é" eacute
+<Multi_key> <apostrophe> <e> : "é" eacute
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_acute() {
+        let mut composer = Composer::from_buffer(&xkb::Context::new(0), TEST_COMPOSE).unwrap();
+        assert_eq!(composer.feed(Keysym::dead_acute), Composed::Composing);
+        assert_eq!(composer.sequence(), "´");
+        assert_eq!(
+            composer.feed(Keysym::e),
+            Composed::Done {
+                keysym: Some(Keysym::eacute),
+                text: "é".to_string()
+            }
+        );
+        assert_eq!(composer.sequence(), "");
+        assert_eq!(composer.feed(Keysym::e), Composed::Nothing);
+        // 修飾鍵不打斷序列
+        assert_eq!(composer.feed(Keysym::Multi_key), Composed::Composing);
+        assert_eq!(composer.feed(Keysym::Shift_L), Composed::Nothing);
+        assert_eq!(composer.feed(Keysym::apostrophe), Composed::Composing);
+        assert_eq!(composer.sequence(), "·'");
+    }
+}
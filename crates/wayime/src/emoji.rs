@@ -0,0 +1,481 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use rime_api::CompositionSnapshot;
+use serde::Deserialize;
+use xkbcommon::xkb::Keysym;
+
+use crate::{
+    binding::{self, KeyBinding},
+    engine::PageInfo,
+    ui::{UiCandidate, UiModel},
+};
+
+/// 內嵌的表情和符號註釋，每行爲字符、英文關鍵詞和中文關鍵詞，首個關鍵詞爲名稱。
+///
+/// 由 `scripts/emoji_annotations.py` 從 emoji-test.txt, `emoji/keywords.tsv` 和
+/// `emoji/keywords_zh.tsv` 生成，聯網重新生成時會併入 CLDR 註釋。英文關鍵詞匹配
+/// 鍵入的查詢，中文關鍵詞匹配 Rime 對查詢的轉換結果。
+const ANNOTATIONS: &str = include_str!("emoji/annotations.tsv");
+
+const PAGE_SIZE: usize = 5;
+/// 最多保存的最近使用表情數
+const MAX_RECENT: usize = 50;
+
+/// 表情選擇配置。
+///
+/// ```toml
+/// [emoji]
+/// keys = ["Control+Alt+e"]
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct EmojiConfig {
+    /// 進入表情選擇的快捷鍵
+    pub keys: Vec<KeyBinding>,
+    /// 最近使用記錄，默認爲 `~/.local/share/wayime/emoji_recent`
+    pub history: Option<PathBuf>,
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        Self {
+            keys: vec![KeyBinding::new(binding::CONTROL | binding::ALT, Keysym::e)],
+            history: None,
+        }
+    }
+}
+
+struct Entry {
+    emoji: &'static str,
+    name: &'static str,
+    /// 英文關鍵詞，已轉小寫
+    keywords: Vec<String>,
+    /// 中文關鍵詞
+    zh_keywords: Vec<&'static str>,
+}
+
+/// 可搜索的表情表。
+struct EmojiIndex {
+    entries: Vec<Entry>,
+}
+
+impl EmojiIndex {
+    fn new() -> Self {
+        let entries = ANNOTATIONS
+            .lines()
+            // `#️⃣` 也以 `#` 開頭
+            .filter(|line| !line.is_empty() && !line.starts_with("# "))
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let emoji = fields.next()?;
+                let en = fields.next()?;
+                let name = en.split('|').next()?;
+                let keywords = en
+                    .split('|')
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(str::to_lowercase)
+                    .collect();
+                let zh_keywords = fields
+                    .next()
+                    .unwrap_or_default()
+                    .split('|')
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect();
+                Some(Entry {
+                    emoji,
+                    name,
+                    keywords,
+                    zh_keywords,
+                })
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// 按查詢和 Rime 轉換出的詞返回匹配的表情序號。
+    ///
+    /// 最近使用的排在前面，其次是英文關鍵詞匹配查詢的，最後是中文關鍵詞匹配
+    /// `words` 的，前者依次爲關鍵詞全等、前綴和包含，後者先按詞的順序。
+    fn search(&self, query: &str, words: &[String], recent: &Recent) -> Vec<usize> {
+        let query = query.to_lowercase();
+        let mut matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let score = best_match(entry.keywords.iter().map(String::as_str), &query)
+                    .map(|score| (0, 0, score))
+                    .or_else(|| {
+                        words.iter().enumerate().find_map(|(i, word)| {
+                            let score = best_match(entry.zh_keywords.iter().copied(), word)?;
+                            Some((1, i, score))
+                        })
+                    })?;
+                let rank = recent.rank(entry.emoji).unwrap_or(usize::MAX);
+                Some((rank, score, index))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_unstable();
+        matches.into_iter().map(|(_, _, index)| index).collect()
+    }
+}
+
+/// 關鍵詞與 `query` 全等爲 0, 前綴爲 1, 包含爲 2, 取最好的。
+fn best_match<'a>(keywords: impl Iterator<Item = &'a str>, query: &str) -> Option<u8> {
+    keywords
+        .filter_map(|keyword| {
+            if keyword == query {
+                Some(0)
+            } else if keyword.starts_with(query) {
+                Some(1)
+            } else if keyword.contains(query) {
+                Some(2)
+            } else {
+                None
+            }
+        })
+        .min()
+}
+
+/// 最近使用的表情，從新到舊。
+struct Recent {
+    path: Option<PathBuf>,
+    items: Vec<String>,
+}
+
+impl Recent {
+    /// 讀取記錄，文件不存在時爲空，`path` 爲 `None` 時不保存。
+    fn load(path: Option<PathBuf>) -> Self {
+        let items = match path.as_deref().map(fs::read_to_string) {
+            Some(Ok(text)) => text.lines().map(str::to_string).collect(),
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => {
+                warn!("Fail to read emoji history: {e}");
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+        Self { path, items }
+    }
+
+    /// 默認記錄路徑。
+    fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("wayime").join("emoji_recent"))
+    }
+
+    fn rank(&self, emoji: &str) -> Option<usize> {
+        self.items.iter().position(|item| item == emoji)
+    }
+
+    /// 記錄使用並保存。
+    fn record(&mut self, emoji: &str) {
+        self.items.retain(|item| item != emoji);
+        self.items.insert(0, emoji.to_string());
+        self.items.truncate(MAX_RECENT);
+        if let Some(path) = &self.path {
+            if let Err(e) = save(path, &self.items) {
+                warn!("Fail to save emoji history: {e}");
+            }
+        }
+    }
+}
+
+fn save(path: &Path, items: &[String]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = items.join("\n");
+    text.push('\n');
+    fs::write(path, text)
+}
+
+/// 表情選擇模式，查詢顯示在預編輯文本中，結果作爲候選。
+pub struct EmojiPicker {
+    index: EmojiIndex,
+    history: Option<PathBuf>,
+    /// 首次進入時讀取
+    recent: Option<Recent>,
+    /// 進行中的查詢，`None` 表示未進入
+    query: Option<String>,
+    /// Rime 對查詢的轉換結果
+    words: Vec<String>,
+    results: Vec<usize>,
+    page: usize,
+    /// 高亮候選的頁內序號
+    highlighted: usize,
+}
+
+impl EmojiPicker {
+    pub fn new(config: &EmojiConfig) -> Self {
+        Self {
+            index: EmojiIndex::new(),
+            history: config.history.clone().or_else(Recent::default_path),
+            recent: None,
+            query: None,
+            words: Vec::new(),
+            results: Vec::new(),
+            page: 0,
+            highlighted: 0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.query.is_some()
+    }
+
+    /// 以空查詢進入，列出最近使用和全部表情。
+    pub fn start(&mut self) {
+        if self.recent.is_none() {
+            self.recent = Some(Recent::load(self.history.clone()));
+        }
+        self.query = Some(String::new());
+        self.search();
+    }
+
+    pub fn stop(&mut self) {
+        self.query = None;
+        self.words.clear();
+        self.results.clear();
+    }
+
+    /// 進行中的查詢。
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// 設置 Rime 對當前查詢的轉換結果，以中文關鍵詞匹配。
+    pub fn set_words(&mut self, words: Vec<String>) {
+        self.words = words;
+        self.search();
+    }
+
+    /// 追加查詢文本，清除舊的轉換結果。
+    pub fn push(&mut self, text: &str) {
+        if let Some(query) = &mut self.query {
+            query.push_str(text);
+            self.words.clear();
+            self.search();
+        }
+    }
+
+    /// 刪除查詢的最後一個字符並清除舊的轉換結果，查詢已空時返回 `false`.
+    pub fn pop(&mut self) -> bool {
+        let popped = self.query.as_mut().and_then(String::pop).is_some();
+        if popped {
+            self.words.clear();
+            self.search();
+        }
+        popped
+    }
+
+    fn search(&mut self) {
+        let (Some(query), Some(recent)) = (&self.query, &self.recent) else {
+            return;
+        };
+        self.results = self.index.search(query, &self.words, recent);
+        self.page = 0;
+        self.highlighted = 0;
+    }
+
+    fn page_range(&self) -> std::ops::Range<usize> {
+        let start = self.page * PAGE_SIZE;
+        start..(start + PAGE_SIZE).min(self.results.len())
+    }
+
+    fn is_last_page(&self) -> bool {
+        (self.page + 1) * PAGE_SIZE >= self.results.len()
+    }
+
+    /// 前後翻頁，返回是否翻頁。
+    pub fn change_page(&mut self, backward: bool) -> bool {
+        if backward && self.page > 0 {
+            self.page -= 1;
+        } else if !backward && !self.is_last_page() {
+            self.page += 1;
+        } else {
+            return false;
+        }
+        self.highlighted = 0;
+        true
+    }
+
+    /// 當前頁是否有第 `index` 個候選。
+    pub fn is_shown(&self, index: usize) -> bool {
+        index < self.page_range().len()
+    }
+
+    /// 高亮當前頁第 `index` 個候選。
+    pub fn highlight(&mut self, index: usize) -> bool {
+        if !self.is_shown(index) {
+            return false;
+        }
+        self.highlighted = index;
+        true
+    }
+
+    /// 移動高亮，越過頁邊時翻頁。
+    pub fn move_highlight(&mut self, backward: bool) {
+        if backward {
+            if self.highlighted > 0 {
+                self.highlighted -= 1;
+            } else if self.change_page(true) {
+                self.highlighted = PAGE_SIZE - 1;
+            }
+        } else if !self.highlight(self.highlighted + 1) {
+            self.change_page(false);
+        }
+    }
+
+    /// 選擇當前頁第 `index` 個候選，默認爲高亮候選，記錄使用並退出。
+    pub fn select(&mut self, index: Option<usize>) -> Option<&'static str> {
+        let index = index.unwrap_or(self.highlighted);
+        let range = self.page_range();
+        let &entry = self
+            .results
+            .get(range.start + index)
+            .filter(|_| index < range.len())?;
+        let emoji = self.index.entries[entry].emoji;
+        if let Some(recent) = &mut self.recent {
+            recent.record(emoji);
+        }
+        self.stop();
+        Some(emoji)
+    }
+
+    /// 查詢前加 `:` 顯示，候選註釋爲英文名稱。
+    pub fn model(&self) -> UiModel {
+        let query = format!(":{}", self.query.as_deref().unwrap_or_default());
+        let len = query.len() as i32;
+        let page = PageInfo {
+            page_no: self.page,
+            page_size: PAGE_SIZE,
+            is_last_page: self.is_last_page(),
+            ..PageInfo::default()
+        };
+        let candidates = self.results[self.page_range()]
+            .iter()
+            .enumerate()
+            .map(|(i, &entry)| {
+                let entry = &self.index.entries[entry];
+                UiCandidate {
+                    label: page.label(i),
+                    text: entry.emoji.to_string(),
+                    comment: Some(entry.name.to_string()),
+                }
+            })
+            .collect::<Vec<_>>();
+        UiModel {
+            composition: CompositionSnapshot {
+                length: len,
+                cursor_pos: len,
+                sel_start: len,
+                sel_end: len,
+                preedit: Some(query),
+            },
+            highlighted: (!candidates.is_empty()).then_some(self.highlighted),
+            candidates,
+            page,
+            ..UiModel::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emojis(index: &EmojiIndex, results: &[usize]) -> Vec<&'static str> {
+        results
+            .iter()
+            .map(|&entry| index.entries[entry].emoji)
+            .collect()
+    }
+
+    #[test]
+    fn search_ranks_exact_before_prefix() {
+        let index = EmojiIndex::new();
+        let recent = Recent::load(None);
+        let results = index.search("cat", &[], &recent);
+        assert_eq!(emojis(&index, &results[..3]), ["😺", "🐱", "🐈"]);
+        // 漢字表情以英文名匹配
+        let results = index.search("congratulations", &[], &recent);
+        assert_eq!(emojis(&index, &results), ["㊗️"]);
+        // 符號
+        let results = index.search("infinity", &[], &recent);
+        assert_eq!(emojis(&index, &results), ["♾️", "∞"]);
+        assert!(index.search("no such emoji", &[], &recent).is_empty());
+    }
+
+    #[test]
+    fn search_matches_chinese_words() {
+        let index = EmojiIndex::new();
+        let recent = Recent::load(None);
+        let words = ["熊猫".to_string()];
+        let results = index.search("xiongmao", &words, &recent);
+        assert_eq!(emojis(&index, &results), ["🐼"]);
+        // 繁體和漢字表情
+        let words = ["熊貓".to_string(), "祝賀".to_string()];
+        let results = index.search("xiongmao", &words, &recent);
+        assert_eq!(emojis(&index, &results), ["🐼", "㊗️"]);
+        // 英文匹配排在中文之前
+        let words = ["猫".to_string()];
+        let results = index.search("cat", &words, &recent);
+        assert_eq!(emojis(&index, &results[..3]), ["😺", "🐱", "🐈"]);
+        assert!(results.len() > index.search("cat", &[], &recent).len());
+        // `#️⃣` 不被當作註釋
+        let results = index.search("keycap: #", &[], &recent);
+        assert_eq!(emojis(&index, &results), ["#️⃣"]);
+    }
+
+    #[test]
+    fn recent_ranks_first_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wayime").join("emoji_recent");
+        let index = EmojiIndex::new();
+        let mut recent = Recent::load(Some(path.clone()));
+        recent.record("🐈");
+        recent.record("😺");
+        recent.record("🐈");
+
+        let recent = Recent::load(Some(path));
+        let results = index.search("cat", &[], &recent);
+        assert_eq!(emojis(&index, &results[..3]), ["🐈", "😺", "🐱"]);
+    }
+
+    #[test]
+    fn picker_pages() {
+        let mut picker = EmojiPicker::new(&EmojiConfig::default());
+        picker.history = None;
+        picker.start();
+        picker.push("heart");
+        assert_eq!(
+            picker.model().composition.preedit.as_deref(),
+            Some(":heart")
+        );
+        assert!(picker.change_page(false));
+        picker.move_highlight(true);
+        assert_eq!(picker.page, 0);
+        assert_eq!(picker.highlighted, PAGE_SIZE - 1);
+        assert!(picker.select(Some(PAGE_SIZE)).is_none());
+        assert!(picker.select(None).is_some());
+        assert!(!picker.is_active());
+    }
+
+    #[test]
+    fn picker_words_follow_query() {
+        let mut picker = EmojiPicker::new(&EmojiConfig::default());
+        picker.history = None;
+        picker.start();
+        picker.push("xiongmao");
+        assert!(picker.results.is_empty());
+        picker.set_words(vec!["熊猫".to_string()]);
+        assert_eq!(picker.model().candidates[0].text, "🐼");
+        // 查詢改變後舊的轉換結果失效
+        picker.pop();
+        assert!(picker.words.is_empty());
+        assert!(picker.results.is_empty());
+    }
+}
@@ -1,134 +1,384 @@
-use log::info;
-use ouroboros::self_referencing;
-use rime_api::{Rime, Session, Traits};
+use log::warn;
+use rime_api::{ContextSnapshot, Rime, RimeHandle, RimeService, SessionId, StatusSnapshot};
+use serde::Serialize;
 use xkbcommon::xkb;
 
-/// 使用單一會話。
-#[self_referencing]
-struct EngineInner {
-    api: Rime,
-    #[borrows(api)]
-    #[covariant]
-    session: Session<'this>,
+/// 當前候選頁信息。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PageInfo {
+    /// 從 0 開始
+    pub page_no: usize,
+    pub page_size: usize,
+    pub is_last_page: bool,
+    pub select_keys: Option<String>,
+    pub select_labels: Vec<String>,
 }
 
-pub struct Engine(EngineInner);
+impl PageInfo {
+    pub fn from_context(context: &ContextSnapshot) -> Self {
+        let menu = &context.menu;
+        Self {
+            page_no: menu.page_no.max(0) as usize,
+            page_size: menu.page_size.max(0) as usize,
+            is_last_page: menu.is_last_page,
+            select_keys: menu.select_keys.clone(),
+            select_labels: context.select_labels.clone(),
+        }
+    }
+
+    /// 是否只有一頁。
+    pub fn is_single(&self) -> bool {
+        self.page_no == 0 && self.is_last_page
+    }
+
+    /// 候選標籤，依次取 `select_labels`, `select_keys` 和數字。
+    pub fn label(&self, index: usize) -> String {
+        if let Some(label) = self.select_labels.get(index) {
+            return label.clone();
+        }
+        if let Some(key) = self
+            .select_keys
+            .as_deref()
+            .and_then(|keys| keys.chars().nth(index))
+        {
+            return key.to_string();
+        }
+        ((index + 1) % 10).to_string()
+    }
+
+    /// 頁碼位置，如 `2/5`.
+    ///
+    /// librime 不提供總頁數，到達最後一頁之前顯示爲 `2/…`.
+    pub fn position(&self) -> String {
+        let page = self.page_no + 1;
+        if self.is_last_page {
+            format!("{page}/{page}")
+        } else {
+            format!("{page}/…")
+        }
+    }
+}
+
+/// 更新界面所需的引擎狀態。
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub context: ContextSnapshot,
+    pub status: StatusSnapshot,
+    /// 原始輸入碼
+    pub input: Option<String>,
+    /// 待提交的文本，取出後即清空
+    pub commit: Option<String>,
+}
+
+/// 是否直接放行按鍵：未在組合或處於 ASCII 模式。
+///
+/// 無法獲取狀態時的默認快照同樣放行。
+pub fn is_bypass(status: &StatusSnapshot) -> bool {
+    !status.is_composing || status.is_ascii_mode
+}
+
+/// 使用單一輸入會話，Rime 運行於專用線程。
+pub struct Engine {
+    handle: RimeHandle,
+    session: SessionId,
+    /// 查詢轉換結果用的會話，不影響輸入中的組合
+    lookup: SessionId,
+}
 
 impl Engine {
     /// 新建輸入法引擎。
-    pub fn new() -> Self {
-        // api
-        let api = Rime::new().expect("fail to create api");
-
-        // traits
-        let shared_data_dir = option_env!("RIME_SHARED_DATA_DIR").unwrap_or("/usr/share/rime-data");
-        let config_dir = dirs::config_dir()
-            .expect("fail to get config dir")
-            .join("wayime")
-            .join("rime");
-        let mut traits = Traits::builder()
-            .shared_data_dir(shared_data_dir)
-            .user_data_dir(&config_dir.to_string_lossy())
-            .distribution_name("wayime")
-            .distribution_code_name("wayime")
-            .distribution_version("0.1.0")
-            .app_name("rime.wayime")
-            .build()
-            .expect("fail to build traits");
-
-        // setup, initialize and maintain
-        api.setup(&mut traits);
-        api.set_notification_handler(|session_id, ty, value| {
-            info!("Handle notification: {session_id} {ty} {value}")
-        });
-        api.initialize(&mut traits);
-        api.start_maintenance(true);
-        api.join_maintenance_thread();
-
-        let inner = EngineInner::new(api, |api| api.create_session());
-
-        Self(inner)
-    }
-
-    /// 獲取會話。
-    pub fn session(&self) -> &Session {
-        self.0.borrow_session()
-    }
-
-    pub fn preedit(&self) -> Preedit {
-        let context = self.session().context();
-        let composition = context.composition();
-        let start = composition.sel_start();
-        let end = composition.sel_end();
-        let text = composition
-            .preedit()
-            .map(|result| result.unwrap().to_string());
-        Preedit { start, end, text }
+    #[cfg(feature = "librime")]
+    pub fn new() -> rime_api::Result<Self> {
+        Self::spawn(init_rime)
+    }
+
+    /// 未鏈接 librime, 只能以 [`Engine::spawn`] 使用模擬後端。
+    #[cfg(not(feature = "librime"))]
+    pub fn new() -> rime_api::Result<Self> {
+        Self::spawn(|| Err(rime_api::Error::LibrimeDisabled))
+    }
+
+    /// 以自定義初始化函數新建引擎，`init` 在 Rime 線程上運行。
+    pub fn spawn<F>(init: F) -> rime_api::Result<Self>
+    where
+        F: FnOnce() -> rime_api::Result<Rime> + Send + 'static,
+    {
+        let handle = RimeService::spawn(init)?;
+        let session = handle.create_session()?;
+        let lookup = handle.create_session()?;
+        Ok(Self {
+            handle,
+            session,
+            lookup,
+        })
+    }
+
+    /// 獲取上下文快照，每次更新只需獲取一次。
+    pub fn context(&self) -> ContextSnapshot {
+        log_err(self.handle.context(self.session)).unwrap_or_default()
+    }
+
+    /// 一次往返獲取上下文、狀態、輸入碼和提交。
+    pub fn snapshot(&self) -> Snapshot {
+        log_err(self.handle.with_session(self.session, |session| {
+            Ok(Snapshot {
+                context: session.context_snapshot()?,
+                status: session.status_snapshot()?,
+                input: session.get_input()?.map(str::to_string),
+                commit: session.commit_snapshot()?.text,
+            })
+        }))
+        .unwrap_or_default()
+    }
+
+    /// 獲取當前頁信息。
+    pub fn page_info(&self) -> PageInfo {
+        PageInfo::from_context(&self.context())
     }
 
     pub fn key(&mut self, key: xkb::Keysym, mods: xkb::ModMask) -> bool {
         let key = key.raw() as i32;
         let mods = mods as i32;
-        self.session().process_key(key, mods)
-    }
-
-    pub fn candidate(&self) -> CandidateInfo {
-        let context = self.session().context();
-        let menu = context.menu();
-        let highlighted_candidate_index = menu.highlighted_candidate_index();
-        // let page_no = menu.page_no();
-        let num_candidates = menu.num_candidates();
-        CandidateInfo {
-            // page_no,
-            highlighted_candidate_index,
-            num_candidates,
-        }
+        log_err(self.handle.process_key(self.session, key, mods)).unwrap_or(false)
     }
 
-    pub fn candidate_get(&self, index: i32) -> Option<String> {
-        let context = self.session().context();
-        let menu = context.menu();
-        let mut candidates = menu.candidates();
-        let cand = candidates.nth(index as usize);
-        let text = cand
-            .flatten()
-            .and_then(|c| c.text().map(|result| result.unwrap().to_string()));
-        text
-    }
-
-    /// 获取
+    /// 獲取待提交的文本。
     pub fn get_commit(&self) -> Option<String> {
-        let commit = self.session().commit();
-        commit.text().map(|res| res.unwrap().to_string())
+        log_err(self.handle.commit(self.session))?.text
     }
 
     /// 切換 ASCII 模式。
     pub fn toggle(&mut self) {
-        let session = self.session();
-        let ascii_mode = session.get_option_c(c"ascii_mode");
-        session.set_option_c(c"ascii_mode", !ascii_mode);
+        log_err(self.handle.with_session(self.session, |session| {
+            let ascii_mode = session.get_option_c(c"ascii_mode")?;
+            session.set_option_c(c"ascii_mode", !ascii_mode)
+        }));
     }
 
     pub fn reset(&mut self) {
-        let session = self.session();
-        session.clear_composition();
-        session.commit_composition();
+        log_err(self.handle.with_session(self.session, |session| {
+            session.clear_composition()?;
+            session.commit_composition()
+        }));
     }
 
-    pub fn is_bypass(&self) -> bool {
-        let status = self.session().status();
-        !status.is_composing() || status.is_ascii_mode()
+    /// 選擇當前頁第 `index` 個候選。
+    pub fn select(&mut self, index: usize) -> bool {
+        log_err(
+            self.handle
+                .select_candidate_on_current_page(self.session, index),
+        )
+        .is_some()
+    }
+
+    /// 高亮當前頁第 `index` 個候選。
+    pub fn highlight(&mut self, index: usize) -> bool {
+        log_err(
+            self.handle
+                .highlight_candidate_on_current_page(self.session, index),
+        )
+        .is_some()
+    }
+
+    /// 從用戶詞典刪除當前頁第 `index` 個候選。
+    pub fn delete(&mut self, index: usize) -> bool {
+        log_err(
+            self.handle
+                .delete_candidate_on_current_page(self.session, index),
+        )
+        .is_some()
+    }
+
+    /// 前後翻頁，返回是否翻頁。
+    pub fn change_page(&mut self, backward: bool) -> bool {
+        log_err(self.handle.change_page(self.session, backward)).unwrap_or(false)
+    }
+
+    /// 獲取狀態快照。
+    pub fn status(&self) -> StatusSnapshot {
+        log_err(self.handle.status(self.session)).unwrap_or_default()
+    }
+
+    /// 當前輸入編碼。
+    pub fn input(&self) -> Option<String> {
+        log_err(self.handle.with_session(self.session, |session| {
+            Ok(session.get_input()?.map(str::to_string))
+        }))
+        .flatten()
+    }
+
+    /// 取出輸入碼並清除組合，用於上屏原始輸入。
+    pub fn commit_code(&mut self) -> String {
+        log_err(self.handle.with_session(self.session, |session| {
+            let code = session.get_input()?.map(str::to_string);
+            session.clear_composition()?;
+            Ok(code)
+        }))
+        .flatten()
+        .unwrap_or_default()
+    }
+
+    pub fn select_schema(&mut self, schema_id: &str) -> rime_api::Result<()> {
+        self.handle.select_schema(self.session, schema_id)?;
+        log_err(self.handle.select_schema(self.lookup, schema_id));
+        Ok(())
+    }
+
+    /// 在查詢會話中轉換 `input`, 返回提交預覽和首頁候選文本。
+    pub fn lookup(&self, input: &str) -> Vec<String> {
+        let input = input.to_string();
+        log_err(self.handle.with_session(self.lookup, move |session| {
+            session.set_input(input)?;
+            let context = session.context_snapshot()?;
+            session.clear_composition()?;
+            let mut words = Vec::new();
+            for text in context
+                .commit_text_preview
+                .into_iter()
+                .chain(context.menu.candidates.into_iter().map(|c| c.text))
+            {
+                if !words.contains(&text) {
+                    words.push(text);
+                }
+            }
+            Ok(words)
+        }))
+        .unwrap_or_default()
+    }
+
+    /// 模擬按鍵序列，如 `nihao{space}`.
+    pub fn simulate(&mut self, key_sequence: &str) -> rime_api::Result<()> {
+        self.handle
+            .simulate_key_sequence(self.session, key_sequence)
     }
 }
 
-pub struct Preedit {
-    pub start: i32,
-    pub end: i32,
-    pub text: Option<String>,
+/// 記錄 Rime 錯誤並轉換爲 `Option`.
+fn log_err<T>(result: rime_api::Result<T>) -> Option<T> {
+    result.inspect_err(|err| warn!("Rime error: {err}")).ok()
 }
 
-pub struct CandidateInfo {
-    // pub page_no: i32,
-    pub highlighted_candidate_index: i32,
-    pub num_candidates: i32,
+/// 在 Rime 線程上初始化 api.
+#[cfg(feature = "librime")]
+fn init_rime() -> rime_api::Result<Rime> {
+    // api
+    let api = Rime::new()?;
+
+    // traits
+    let shared_data_dir = option_env!("RIME_SHARED_DATA_DIR").unwrap_or("/usr/share/rime-data");
+    let config_dir = dirs::config_dir()
+        .expect("fail to get config dir")
+        .join("wayime")
+        .join("rime");
+    let mut traits = rime_api::Traits::builder()
+        .shared_data_dir(shared_data_dir)
+        .user_data_dir(&config_dir.to_string_lossy())
+        .distribution_name("wayime")
+        .distribution_code_name("wayime")
+        .distribution_version("0.1.0")
+        .app_name("rime.wayime")
+        .build()?;
+
+    // setup, initialize and maintain
+    api.setup(&mut traits)?;
+    api.set_notification_handler(|session_id, ty, value| {
+        log::info!("Handle notification: {session_id} {ty} {value}")
+    })?;
+    api.initialize(&mut traits)?;
+    api.start_maintenance(true)?;
+    api.join_maintenance_thread()?;
+
+    Ok(api)
+}
+
+#[cfg(test)]
+mod tests {
+    use rime_api::MockDictionary;
+
+    use super::*;
+
+    fn engine() -> Engine {
+        let dictionary = MockDictionary::new()
+            .page_size(3)
+            .entry("nihao", ["你好", "擬好"]);
+        Engine::spawn(|| Ok(Rime::mock(dictionary))).unwrap()
+    }
+
+    fn type_str(engine: &mut Engine, s: &str) {
+        for c in s.chars() {
+            assert!(engine.key(xkb::Keysym::new(c as u32), 0));
+        }
+    }
+
+    #[test]
+    fn compose_and_commit() {
+        let mut engine = engine();
+        assert!(is_bypass(&engine.status()));
+        type_str(&mut engine, "nihao");
+        assert!(!is_bypass(&engine.status()));
+
+        let context = engine.context();
+        assert_eq!(context.composition.preedit.as_deref(), Some("nihao"));
+        assert_eq!(context.menu.candidates.len(), 2);
+
+        assert!(engine.key(xkb::Keysym::space, 0));
+        assert_eq!(engine.get_commit().as_deref(), Some("你好"));
+        assert_eq!(engine.get_commit(), None);
+        assert!(is_bypass(&engine.status()));
+    }
+
+    #[test]
+    fn toggle_ascii_mode() {
+        let mut engine = engine();
+        engine.toggle();
+        assert!(!engine.key(xkb::Keysym::n, 0));
+        assert!(is_bypass(&engine.status()));
+        engine.toggle();
+        assert!(engine.key(xkb::Keysym::n, 0));
+    }
+
+    #[test]
+    fn page_navigation() {
+        let dictionary = MockDictionary::new()
+            .page_size(2)
+            .entry("ma", ["嗎", "媽", "馬"]);
+        let mut engine = Engine::spawn(|| Ok(Rime::mock(dictionary))).unwrap();
+        type_str(&mut engine, "ma");
+
+        let page = engine.page_info();
+        assert_eq!((page.page_no, page.page_size), (0, 2));
+        assert!(!page.is_last_page);
+        assert_eq!(page.position(), "1/…");
+
+        assert!(engine.change_page(false));
+        assert_eq!(engine.page_info().position(), "2/2");
+        assert!(!engine.change_page(false));
+
+        assert!(engine.change_page(true));
+        assert!(engine.highlight(1));
+        assert_eq!(engine.context().menu.highlighted_candidate_index, 1);
+        assert!(engine.delete(1));
+        assert_eq!(engine.context().menu.candidates[1].text, "馬");
+        assert!(engine.select(0));
+        assert_eq!(engine.get_commit().as_deref(), Some("嗎"));
+    }
+
+    #[test]
+    fn lookup_keeps_composition() {
+        let mut engine = engine();
+        type_str(&mut engine, "ni");
+        assert_eq!(engine.lookup("nihao"), ["你好", "擬好"]);
+        assert!(engine.lookup("xyz").is_empty());
+        assert_eq!(engine.input().as_deref(), Some("ni"));
+    }
+
+    #[test]
+    fn reset_clears_composition() {
+        let mut engine = engine();
+        type_str(&mut engine, "ni");
+        engine.reset();
+        assert!(is_bypass(&engine.status()));
+        assert_eq!(engine.context().composition.preedit, None);
+        assert_eq!(engine.get_commit(), None);
+    }
 }
@@ -1,4 +1,17 @@
-use wayland_client::protocol::wl_seat::WlSeat;
+use std::{collections::BTreeSet, os::fd::OwnedFd};
+
+use log::warn;
+use wayland_client::{
+    protocol::{
+        wl_compositor::WlCompositor, wl_keyboard::KeymapFormat, wl_pointer::WlPointer,
+        wl_seat::WlSeat, wl_shm::WlShm,
+    },
+    QueueHandle,
+};
+use wayland_protocols::wp::{
+    fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    viewporter::client::wp_viewporter::WpViewporter,
+};
 use wayland_protocols_misc::{
     zwp_input_method_v2::client::{
         zwp_input_method_keyboard_grab_v2::ZwpInputMethodKeyboardGrabV2,
@@ -10,17 +23,33 @@ use wayland_protocols_misc::{
         zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
     },
 };
-use xkbcommon::xkb::{self, Keysym};
+use xkbcommon::xkb;
 
-use crate::{engine::Engine, Config};
+use self::dispatch_pointer::PointerState;
+use crate::{
+    clock::{EventTime, MonotonicClock},
+    codepoint::CodepointInput,
+    compose::Composer,
+    emoji::EmojiPicker,
+    engine::Engine,
+    keymap::KeyResolver,
+    record::{Record, Recorder},
+    switch::TapDetector,
+    ui::{self, CandidateRenderer, Globals},
+    Config,
+};
 
 mod dispatch_input_method;
 mod dispatch_input_method_keyboard_grab;
 mod dispatch_input_method_manager;
+mod dispatch_pointer;
+mod dispatch_popup;
 mod dispatch_registry;
 mod dispatch_seat;
 mod dispatch_virtual_keyboard;
 mod dispatch_virtual_keyboard_manager;
+#[cfg(test)]
+mod tests;
 
 pub struct Im {
     config: Config,
@@ -29,8 +58,23 @@ pub struct Im {
     // xkb
     context: xkb::Context,
     state: Option<xkb::State>,
+    // 上次收到的 keymap, 用於跳過重複上傳
+    keymap: Option<(KeymapFormat, String)>,
+    // 虛擬鍵盤創建前收到的 keymap, 創建後上傳
+    pending_keymap: Option<(KeymapFormat, OwnedFd, u32)>,
+    // 虛擬鍵盤創建前收到的修飾鍵狀態，依次爲 depressed, latched, locked 和 group
+    pending_modifiers: Option<(u32, u32, u32, u32)>,
+    resolver: KeyResolver,
+    composer: Option<Composer>,
     // wayland core
     seat: Option<WlSeat>,
+    compositor: Option<WlCompositor>,
+    shm: Option<WlShm>,
+    pointer: Option<WlPointer>,
+    pointer_state: PointerState,
+    // 彈出窗口縮放
+    fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
+    viewporter: Option<WpViewporter>,
     // input method
     input_method_manager: Option<ZwpInputMethodManagerV2>,
     input_method: Option<ZwpInputMethodV2>,
@@ -38,38 +82,133 @@ pub struct Im {
     // virtual keyboard
     virtual_keyboard_manager: Option<ZwpVirtualKeyboardManagerV1>,
     virtual_keyboard: Option<ZwpVirtualKeyboardV1>,
-    // records
-    records: [Option<Keysym>; 2],
+    // 已轉發按下、尚未轉發鬆開的鍵碼
+    forwarded_keys: BTreeSet<u32>,
+    // 合成按鍵事件的時間戳
+    time: EventTime,
+    // 切換鍵單擊檢測
+    tap: TapDetector,
+    // 臨時英文模式下輸入的文本
+    inline_ascii: Option<String>,
+    // 表情選擇
+    emoji: EmojiPicker,
+    // 碼位輸入，不經 Rime
+    codepoint: Option<CodepointInput>,
     // serial
     serial: u32,
+    // 刪除候選的提示，下次按鍵時清除
+    notice: Option<String>,
+    // 候選界面
+    renderer: Box<dyn CandidateRenderer>,
+    // 事件記錄
+    recorder: Option<Recorder>,
 }
 
 impl Im {
     pub fn new(config: Config) -> Self {
-        let engine = Engine::new();
+        let engine = Engine::new().expect("fail to create engine");
+        Self::with_engine(config, engine)
+    }
+
+    /// 以給定引擎新建輸入法。
+    pub fn with_engine(config: Config, engine: Engine) -> Self {
+        let renderer = ui::renderer(&config);
+        Self::with_renderer(config, engine, renderer)
+    }
+
+    /// 以給定引擎和候選界面新建輸入法。
+    pub fn with_renderer(
+        config: Config,
+        engine: Engine,
+        renderer: Box<dyn CandidateRenderer>,
+    ) -> Self {
         let context = xkb::Context::new(0);
-        let records = [None; 2];
+        let resolver = KeyResolver::new(&context, config.key_mapping);
+        let composer = Composer::from_env(&context);
+        let tap = TapDetector::new(config.switch_key, config.switch_timeout);
         let serial = 0;
-        let im = Self {
+        Self {
             engine,
             context,
             state: None,
+            keymap: None,
+            pending_keymap: None,
+            pending_modifiers: None,
+            resolver,
+            composer,
             seat: None,
+            compositor: None,
+            shm: None,
+            pointer: None,
+            pointer_state: PointerState::default(),
+            fractional_scale_manager: None,
+            viewporter: None,
             input_method_manager: None,
             input_method: None,
             input_method_keyboard_grab: None,
             virtual_keyboard_manager: None,
             virtual_keyboard: None,
-            records,
+            forwarded_keys: BTreeSet::new(),
+            time: EventTime::new(MonotonicClock::default()),
+            tap,
+            inline_ascii: None,
+            emoji: EmojiPicker::new(&config.emoji),
+            codepoint: None,
             serial,
+            notice: None,
+            renderer,
             config,
-        };
-        im
+            recorder: None,
+        }
+    }
+
+    /// 記錄此後的事件和動作。
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// 替換候選界面。
+    #[cfg(test)]
+    pub fn set_renderer(&mut self, renderer: impl CandidateRenderer + 'static) {
+        self.renderer = Box::new(renderer);
+    }
+
+    /// 替換合成事件使用的時鐘。
+    #[cfg(test)]
+    pub fn set_clock(&mut self, clock: impl crate::clock::Clock + 'static) {
+        self.time = EventTime::new(clock);
+    }
+
+    /// 將已綁定的 Wayland 對象交給渲染器。
+    fn bind_renderer(&mut self, qh: &QueueHandle<Self>) {
+        self.renderer.bind(&Globals {
+            qh,
+            compositor: self.compositor.as_ref(),
+            shm: self.shm.as_ref(),
+            input_method: self.input_method.as_ref(),
+            fractional_scale_manager: self.fractional_scale_manager.as_ref(),
+            viewporter: self.viewporter.as_ref(),
+        });
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// 寫入記錄，失敗後停止記錄。
+    fn record(&mut self, record: impl Into<Record>) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.write(record.into()) {
+                warn!("Fail to write record, stop recording: {err}");
+                self.recorder = None;
+            }
+        }
     }
 }
 
 impl Drop for Im {
     fn drop(&mut self) {
+        self.release_forwarded_keys(self.time.now());
         if let Some(grab) = &self.input_method_keyboard_grab {
             grab.release();
         }
@@ -4,6 +4,7 @@ use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_v2::{
 };
 
 use super::Im;
+use crate::record;
 
 impl Dispatch<ZwpInputMethodV2, ()> for Im {
     fn event(
@@ -14,6 +15,7 @@ impl Dispatch<ZwpInputMethodV2, ()> for Im {
         _: &wayland_client::Connection,
         _: &QueueHandle<Self>,
     ) {
+        im.record_event(&event);
         match event {
             Event::Activate => {
                 im.handle_reset();
@@ -42,7 +44,42 @@ impl Dispatch<ZwpInputMethodV2, ()> for Im {
 }
 
 impl Im {
+    /// 記錄輸入法事件，不含周圍文本內容。
+    fn record_event(&mut self, event: &Event) {
+        if !self.is_recording() {
+            return;
+        }
+        let event = match event {
+            Event::Activate => record::Event::Activate,
+            Event::Deactivate => record::Event::Deactivate,
+            Event::SurroundingText { cursor, anchor, .. } => record::Event::SurroundingText {
+                cursor: *cursor,
+                anchor: *anchor,
+            },
+            Event::TextChangeCause { cause } => record::Event::TextChangeCause {
+                cause: (*cause).into(),
+            },
+            Event::ContentType { hint, purpose } => record::Event::ContentType {
+                hint: (*hint).into(),
+                purpose: (*purpose).into(),
+            },
+            Event::Done => record::Event::Done,
+            Event::Unavailable => record::Event::Unavailable,
+            _ => return,
+        };
+        self.record(event);
+    }
+
     fn handle_reset(&mut self) {
+        self.release_forwarded_keys(self.time.now());
+        self.tap.cancel();
+        self.inline_ascii = None;
+        self.emoji.stop();
+        self.codepoint = None;
+        self.notice = None;
+        if let Some(composer) = &mut self.composer {
+            composer.reset();
+        }
         self.engine.reset();
     }
 
@@ -1,9 +1,13 @@
 use std::{
-    os::fd::{AsFd, OwnedFd},
-    time::{SystemTime, UNIX_EPOCH},
+    fs::File,
+    os::{
+        fd::{AsFd, BorrowedFd, OwnedFd},
+        unix::fs::FileExt,
+    },
 };
 
-use log::info;
+use log::{info, warn};
+use rime_api::StatusSnapshot;
 use wayland_client::{
     protocol::wl_keyboard::{KeyState, KeymapFormat},
     Connection, Dispatch, QueueHandle, WEnum,
@@ -13,12 +17,23 @@ use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_keyboa
 };
 use xkbcommon::xkb::{
     self,
-    ffi::{XKB_STATE_LAYOUT_EFFECTIVE, XKB_STATE_MODS_EFFECTIVE},
-    KeyDirection, Keycode, Keysym, KEYMAP_COMPILE_NO_FLAGS, KEYMAP_FORMAT_TEXT_V1,
-    KEYMAP_FORMAT_USE_ORIGINAL,
+    ffi::{
+        XKB_STATE_LAYOUT_EFFECTIVE, XKB_STATE_MODS_DEPRESSED, XKB_STATE_MODS_EFFECTIVE,
+        XKB_STATE_MODS_LATCHED,
+    },
+    KeyDirection, Keycode, Keysym, ModMask, KEYMAP_COMPILE_NO_FLAGS, KEYMAP_FORMAT_TEXT_V1,
 };
 
 use super::Im;
+use crate::binding;
+use crate::codepoint::CodepointInput;
+use crate::compose::Composed;
+use crate::engine::{is_bypass, Snapshot};
+use crate::keymap::carry_state;
+use crate::preedit::Preedit;
+use crate::record::{keymap_hash, Action, Event as RecordEvent};
+use crate::switch::{CapsLock, SwitchAction};
+use crate::ui::UiModel;
 
 /// 處理鍵盤抓取事件
 impl Dispatch<ZwpInputMethodKeyboardGrabV2, ()> for Im {
@@ -65,49 +80,106 @@ impl Dispatch<ZwpInputMethodKeyboardGrabV2, ()> for Im {
 
 impl Im {
     /// 處理 keymap, 創建自己的 xkb_state.
+    ///
+    /// 內容未變時不重新上傳；解析失敗時不再經過 Rime, 原樣轉發按鍵。
     fn handle_keymap(&mut self, format: WEnum<KeymapFormat>, fd: OwnedFd, size: u32) {
         info!("Handle keymap, format: {format:?}, fd: {fd:?}, size: {size}");
-        let format = format.into_result().expect("invalid format enum");
-        // 更新 keyboard 鍵盤
-        self.virtual_keyboard
-            .as_ref()
-            .unwrap()
-            .keymap(format.into(), fd.as_fd(), size);
+        let text = read_keymap(&fd, size)
+            .inspect_err(|err| warn!("Fail to read keymap: {err}"))
+            .ok();
+        if self.is_recording() {
+            let text = text.clone().unwrap_or_default();
+            self.record(RecordEvent::Keymap {
+                format: format.into(),
+                hash: keymap_hash(&text),
+                text,
+            });
+        }
+        let format = match format.into_result() {
+            Ok(format) => format,
+            Err(err) => {
+                warn!("Ignore keymap: {err}");
+                return;
+            }
+        };
+        let keymap = text.map(|text| (format, text));
+        if keymap.is_some() && keymap == self.keymap {
+            info!("Keymap unchanged");
+            return;
+        }
+        // 更新 keyboard 鍵盤，尚未創建時留待創建後上傳
+        if self.virtual_keyboard.is_some() {
+            self.forward_keymap(format, fd.as_fd(), size);
+        } else {
+            self.pending_keymap = Some((format, fd, size));
+        }
         // 設置 XKB keymap 和狀態
-        let xkb_keymap = unsafe {
-            xkb::Keymap::new_from_fd(
+        let xkb_keymap = match &keymap {
+            Some((KeymapFormat::XkbV1, text)) => xkb::Keymap::new_from_string(
                 &self.context,
-                fd,
-                size as usize,
-                match format {
-                    KeymapFormat::NoKeymap => KEYMAP_FORMAT_USE_ORIGINAL,
-                    KeymapFormat::XkbV1 => KEYMAP_FORMAT_TEXT_V1,
-                    _ => unreachable!(),
-                },
+                text.clone(),
+                KEYMAP_FORMAT_TEXT_V1,
                 KEYMAP_COMPILE_NO_FLAGS,
-            )
-        }
-        .unwrap()
-        .unwrap();
-        self.state = Some(xkb::State::new(&xkb_keymap));
+            ),
+            _ => None,
+        };
+        self.state = match xkb_keymap {
+            Some(xkb_keymap) => Some(match &self.state {
+                Some(state) => carry_state(state, &xkb_keymap),
+                None => xkb::State::new(&xkb_keymap),
+            }),
+            None => {
+                if format == KeymapFormat::XkbV1 {
+                    warn!("Fail to compile keymap, forward keys untouched");
+                } else {
+                    info!("No keymap, forward keys untouched");
+                }
+                None
+            }
+        };
+        self.keymap = keymap;
     }
 
     /// 處理按鍵事件。
-    fn handle_key(&mut self, _serial: u32, _time: u32, key: u32, key_state: WEnum<KeyState>) {
-        let state = self.state.as_ref().unwrap();
-        // xkb 轉換
+    fn handle_key(&mut self, serial: u32, time: u32, key: u32, key_state: WEnum<KeyState>) {
+        self.record(RecordEvent::Key {
+            serial,
+            time,
+            key,
+            state: key_state.into(),
+        });
+        self.time.observe(time);
         let keycode = Keycode::new(key + 8);
-        let keysym = state.key_get_one_sym(keycode);
-        info!("Handle key: {:?}", keysym);
         // 獲取 key state
         let key_state = key_state.into_result().expect("unrecognized key state");
         let pressed = key_state == KeyState::Pressed;
+        // 沒有可用的 keymap 時無從轉換
+        let Some(state) = &self.state else {
+            self.forward_key(keycode, pressed, time);
+            return;
+        };
+        // xkb 轉換
+        let keysyms = self.resolver.keysyms(state, keycode);
+        info!("Handle key: {:?}", keysyms);
         // TODO: 處理 repeat
-        self.handle_key_further(keycode, keysym, pressed);
+        self.handle_key_further(keycode, &keysyms, pressed, time);
     }
 
-    /// 進一步處理
-    fn handle_key_further(&mut self, keycode: Keycode, keysym: Keysym, pressed: bool) {
+    /// 進一步處理，`time` 爲按鍵事件的時間戳
+    fn handle_key_further(
+        &mut self,
+        keycode: Keycode,
+        keysyms: &[Keysym],
+        pressed: bool,
+        time: u32,
+    ) {
+        // 按下前是否按住了其他修飾鍵
+        let held = self
+            .state
+            .as_ref()
+            .unwrap()
+            .serialize_mods(XKB_STATE_MODS_DEPRESSED | XKB_STATE_MODS_LATCHED)
+            != 0;
         // 更新 state
         self.state.as_mut().unwrap().update_key(
             keycode,
@@ -117,155 +189,574 @@ impl Im {
                 KeyDirection::Up
             },
         );
+        if pressed {
+            self.notice = None;
+        }
+        let mods = self
+            .state
+            .as_ref()
+            .unwrap()
+            .serialize_mods(XKB_STATE_MODS_EFFECTIVE | XKB_STATE_LAYOUT_EFFECTIVE);
+        // 快捷鍵只看第一個 keysym
+        let keysym = keysyms.first().copied().unwrap_or(Keysym::NoSymbol);
+        // 每次往返都阻塞 Rime 線程，只在改變狀態後重新獲取
+        let mut status = self.engine.status();
         let mut handled = false;
-        // toggle
-        if !handled && self.should_toggle(keysym, pressed) {
-            self.engine.toggle();
+        // 表情選擇中按鍵都由其處理
+        if self.emoji.is_active() {
+            if pressed {
+                self.emoji_key(keysym);
+            }
             handled = true;
         }
-        // 如果是按下
+        // 碼位輸入中按鍵都由其處理
+        if !handled && self.codepoint.is_some() {
+            if pressed {
+                self.codepoint_key(keysym);
+            }
+            handled = true;
+        }
+        // 進入碼位輸入
+        if !handled
+            && pressed
+            && (self.is_codepoint_key(keysym, mods) || self.codepoint_prefix(keysym, &status))
+        {
+            self.tap.cancel();
+            self.codepoint = Some(CodepointInput::default());
+            handled = true;
+        }
+        // 進入表情選擇
+        if !handled && pressed && self.is_emoji_key(keysym, mods) {
+            self.tap.cancel();
+            self.emoji.start();
+            handled = true;
+        }
+        // 單擊切換鍵
+        if !handled && self.tap.key(keysym, pressed, held, time) {
+            self.switch_mode(&status, time);
+            handled = true;
+        }
+        // 刪除候選
+        if !handled && pressed && self.is_delete_candidate_key(keysym, mods) {
+            handled = self.delete_candidate();
+        }
+        // 臨時英文
         if !handled && pressed {
-            // 發送按鍵信息到 Rime
-            handled = self.engine.key(
-                keysym,
-                self.state
-                    .as_mut()
-                    .unwrap()
-                    .serialize_mods(XKB_STATE_MODS_EFFECTIVE | XKB_STATE_LAYOUT_EFFECTIVE),
-            );
-        }
-        // bypass 模式
-        if !handled && self.engine.is_bypass() {
-            // 直接原樣寫入文本
-            let keyboard = self.virtual_keyboard.as_ref().unwrap();
-            keyboard.key(
-                time_ms(),
-                keycode.raw() - 8,
-                if pressed {
-                    KeyState::Pressed
-                } else {
-                    KeyState::Released
-                } as u32,
-            );
+            handled = self.inline_ascii_key(keysym);
+        }
+        // 大寫鎖定時不經 Rime
+        let caps_bypass =
+            !handled && self.inline_ascii.is_none() && self.caps_lock_bypass(mods, &status);
+        if caps_bypass && pressed && status.is_composing {
+            let code = self.engine.commit_code();
+            self.commit_string(code);
+            self.refresh();
+        }
+        // 組合中 Shift 加字母
+        if !handled && !caps_bypass && pressed {
+            handled = self.shift_letter(keysym, mods, &status);
+        }
+        // 如果是按下
+        if !handled && !caps_bypass && pressed {
+            // 發送按鍵信息到 Rime, keysym 已按大寫鎖定轉換，不再傳 Lock
+            handled = self.feed_keysyms(keysyms, mods & !binding::LOCK, &status);
+            if handled {
+                status = self.engine.status();
+                self.check_prefix(&status);
+            }
+        }
+        if !pressed && self.forwarded_keys.contains(&keycode.raw()) {
+            // 按下已轉發，無論當前模式都轉發鬆開
+            self.forward_key(keycode, pressed, time);
+            if handled {
+                self.refresh();
+            }
+        } else if !handled && self.inline_ascii.is_none() && (caps_bypass || is_bypass(&status)) {
+            // bypass 模式直接原樣寫入文本
+            self.forward_key(keycode, pressed, time);
         } else {
-            self.update_preedit_panel();
-            if let Some(commit) = self.engine.get_commit() {
-                self.commit_string(commit);
+            self.refresh();
+        }
+    }
+
+    /// 單擊切換鍵，組合中按 `switch-action` 處理。
+    ///
+    /// 先鬆開已轉發的按鍵，切換後不再與客戶端中按住的鍵相關。
+    fn switch_mode(&mut self, status: &StatusSnapshot, time: u32) {
+        self.release_forwarded_keys(time);
+        // 再次單擊結束臨時英文
+        if self.inline_ascii.is_some() {
+            self.finish_inline_ascii();
+            return;
+        }
+        if !status.is_composing {
+            self.engine.toggle();
+            return;
+        }
+        match self.config.switch_action {
+            SwitchAction::CommitCode => {
+                let code = self.engine.commit_code();
+                if !code.is_empty() {
+                    self.commit_string(code);
+                }
+                self.engine.toggle();
+            }
+            SwitchAction::Clear => {
+                self.engine.commit_code();
+                self.engine.toggle();
+            }
+            SwitchAction::InlineAscii => self.inline_ascii = Some(String::new()),
+            SwitchAction::Noop => {}
+        }
+    }
+
+    /// 臨時英文模式下處理按下的鍵，返回是否處理。
+    ///
+    /// 可打印字符追加到英文，空格和回車上屏，不產生字符的鍵照常處理。
+    fn inline_ascii_key(&mut self, keysym: Keysym) -> bool {
+        let Some(text) = &mut self.inline_ascii else {
+            return false;
+        };
+        match keysym {
+            Keysym::space | Keysym::Return | Keysym::KP_Enter => self.finish_inline_ascii(),
+            Keysym::BackSpace => {
+                if text.pop().is_none() {
+                    self.inline_ascii = None;
+                }
+            }
+            // 放棄英文，保留輸入碼
+            Keysym::Escape => self.inline_ascii = None,
+            _ => {
+                let utf8 = xkb::keysym_to_utf8(keysym);
+                let utf8 = utf8.trim_end_matches('\0');
+                if utf8.is_empty() || utf8.chars().any(char::is_control) {
+                    return false;
+                }
+                text.push_str(utf8);
+            }
+        }
+        true
+    }
+
+    /// 上屏輸入碼和臨時英文，回到原來的模式。
+    fn finish_inline_ascii(&mut self) {
+        if let Some(text) = self.inline_ascii.take() {
+            let code = self.engine.commit_code() + &text;
+            if !code.is_empty() {
+                self.commit_string(code);
             }
-            self.input_method.as_ref().unwrap().commit(self.serial);
         }
     }
 
+    /// 組合中 Shift 加字母進入臨時英文，返回是否進入。
+    fn shift_letter(&mut self, keysym: Keysym, mods: ModMask, status: &StatusSnapshot) -> bool {
+        let letter = (Keysym::A.raw()..=Keysym::Z.raw()).contains(&keysym.raw());
+        if !letter || mods & binding::SHIFT == 0 || !status.is_composing || status.is_ascii_mode {
+            return false;
+        }
+        if !self
+            .config
+            .inline_ascii(status.schema_id.as_deref())
+            .shift_letter
+        {
+            return false;
+        }
+        self.inline_ascii = Some(char::from(keysym.raw() as u8).to_string());
+        true
+    }
+
+    /// 輸入碼恰爲配置的前綴時清除並進入臨時英文。
+    fn check_prefix(&mut self, status: &StatusSnapshot) {
+        let prefixes = &self
+            .config
+            .inline_ascii(status.schema_id.as_deref())
+            .prefixes;
+        if status.is_ascii_mode || !status.is_composing || prefixes.is_empty() {
+            return;
+        }
+        if self
+            .engine
+            .input()
+            .is_some_and(|input| prefixes.contains(&input))
+        {
+            self.engine.commit_code();
+            self.inline_ascii = Some(String::new());
+        }
+    }
+
+    /// 大寫鎖定且方案配置爲不經 Rime.
+    fn caps_lock_bypass(&self, mods: ModMask, status: &StatusSnapshot) -> bool {
+        mods & binding::LOCK != 0
+            && self
+                .config
+                .inline_ascii(status.schema_id.as_deref())
+                .caps_lock
+                == CapsLock::Bypass
+    }
+
+    /// 表情選擇中處理按下的鍵。
+    ///
+    /// 可打印字符追加到查詢，空格和回車選擇高亮候選。數字鍵在當前頁有對應候選時
+    /// 選擇該候選，否則追加到查詢，因此數字只能在結果少於其序號時輸入查詢。
+    /// 查詢改變後交給 Rime 轉換，以結果匹配中文關鍵詞。
+    fn emoji_key(&mut self, keysym: Keysym) {
+        // ASCII 字符的 keysym 即其碼位
+        let digit = keysym.raw().wrapping_sub(u32::from(b'1'));
+        let selected = match keysym {
+            Keysym::Escape => {
+                self.emoji.stop();
+                None
+            }
+            Keysym::space | Keysym::Return | Keysym::KP_Enter => self.emoji.select(None),
+            _ if digit < 9 && self.emoji.is_shown(digit as usize) => {
+                self.emoji.select(Some(digit as usize))
+            }
+            Keysym::BackSpace => {
+                if self.emoji.pop() {
+                    self.lookup_emoji_words();
+                } else {
+                    self.emoji.stop();
+                }
+                None
+            }
+            Keysym::Up | Keysym::Down => {
+                self.emoji.move_highlight(keysym == Keysym::Up);
+                None
+            }
+            Keysym::Page_Up | Keysym::Page_Down => {
+                self.emoji.change_page(keysym == Keysym::Page_Up);
+                None
+            }
+            _ => {
+                let utf8 = xkb::keysym_to_utf8(keysym);
+                let utf8 = utf8.trim_end_matches('\0');
+                if !utf8.is_empty() && !utf8.chars().any(char::is_control) {
+                    self.emoji.push(utf8);
+                    self.lookup_emoji_words();
+                }
+                None
+            }
+        };
+        if let Some(emoji) = selected {
+            self.commit_string(emoji.to_string());
+        }
+    }
+
+    fn lookup_emoji_words(&mut self) {
+        let words = match self.emoji.query() {
+            Some(query) if !query.is_empty() => self.engine.lookup(query),
+            _ => return,
+        };
+        self.emoji.set_words(words);
+    }
+
+    /// 碼位輸入中處理按下的鍵，空格和回車提交有效的字符。
+    fn codepoint_key(&mut self, keysym: Keysym) {
+        let Some(input) = &mut self.codepoint else {
+            return;
+        };
+        match keysym {
+            Keysym::Escape => self.codepoint = None,
+            Keysym::BackSpace => {
+                if !input.pop() {
+                    self.codepoint = None;
+                }
+            }
+            Keysym::space | Keysym::Return | Keysym::KP_Enter => {
+                // 無效碼位不提交
+                if let Some(c) = input.char() {
+                    self.codepoint = None;
+                    self.commit_string(c.to_string());
+                }
+            }
+            _ => {
+                if let Some(digit) = char::from_u32(xkb::keysym_to_utf32(keysym)) {
+                    input.push(digit);
+                }
+            }
+        }
+    }
+
+    fn is_codepoint_key(&self, keysym: Keysym, mods: ModMask) -> bool {
+        self.config
+            .codepoint
+            .keys
+            .iter()
+            .any(|binding| binding.matches(keysym, mods))
+    }
+
+    /// 組合中輸入碼加上此鍵的字符是否等於碼位前綴，是則清除輸入碼。
+    fn codepoint_prefix(&mut self, keysym: Keysym, status: &StatusSnapshot) -> bool {
+        let Some(prefix) = &self.config.codepoint.prefix else {
+            return false;
+        };
+        let utf8 = xkb::keysym_to_utf8(keysym);
+        let utf8 = utf8.trim_end_matches('\0');
+        // 只在按下前綴的最後一個字符時查詢輸入碼
+        let ends_prefix = !utf8.is_empty() && prefix.to_lowercase().ends_with(&utf8.to_lowercase());
+        if !status.is_composing || !ends_prefix {
+            return false;
+        }
+        let Some(mut code) = self.engine.input() else {
+            return false;
+        };
+        code.push_str(utf8);
+        if !code.eq_ignore_ascii_case(prefix) {
+            return false;
+        }
+        self.engine.commit_code();
+        true
+    }
+
+    fn is_emoji_key(&self, keysym: Keysym, mods: ModMask) -> bool {
+        self.config
+            .emoji
+            .keys
+            .iter()
+            .any(|binding| binding.matches(keysym, mods))
+    }
+
+    /// 依次將 keysym 經 Compose 表發送給 Rime, 返回是否處理。
+    fn feed_keysyms(&mut self, keysyms: &[Keysym], mods: ModMask, status: &StatusSnapshot) -> bool {
+        let mut handled = false;
+        for &keysym in keysyms {
+            handled |= self.feed_keysym(keysym, mods, status);
+        }
+        handled
+    }
+
+    fn feed_keysym(&mut self, keysym: Keysym, mods: ModMask, status: &StatusSnapshot) -> bool {
+        // ASCII 模式下由客戶端組合，未在組合時也要組合死鍵
+        let composed = match &mut self.composer {
+            Some(composer) if !status.is_ascii_mode => composer.feed(keysym),
+            _ => Composed::Nothing,
+        };
+        match composed {
+            Composed::Nothing => self.engine.key(keysym, mods),
+            Composed::Composing | Composed::Cancelled => true,
+            // Rime 不處理時直接提交組合結果
+            Composed::Done { keysym, text } => {
+                let fed = keysym.is_some_and(|keysym| self.engine.key(keysym, mods));
+                if !fed && !text.is_empty() {
+                    self.commit_string(text);
+                }
+                true
+            }
+        }
+    }
+
+    /// 經虛擬鍵盤原樣轉發按鍵，按下未轉發的鬆開不轉發。
+    ///
+    /// 虛擬鍵盤尚未創建時丟棄按鍵。
+    fn forward_key(&mut self, keycode: Keycode, pressed: bool, time: u32) {
+        if self.virtual_keyboard.is_none() {
+            warn!("No virtual keyboard, drop key {}", keycode.raw());
+            return;
+        }
+        if pressed {
+            self.forwarded_keys.insert(keycode.raw());
+        } else if !self.forwarded_keys.remove(&keycode.raw()) {
+            return;
+        }
+        let keyboard = self.virtual_keyboard.as_ref().unwrap();
+        let key = keycode.raw() - 8;
+        let state = if pressed {
+            KeyState::Pressed
+        } else {
+            KeyState::Released
+        } as u32;
+        keyboard.key(time, key, state);
+        self.record(Action::ForwardKey { key, state });
+    }
+
+    /// 鬆開所有已轉發按下的鍵，用於切換模式、停用輸入法或釋放抓取時。
+    pub(super) fn release_forwarded_keys(&mut self, time: u32) {
+        let keycodes = self.forwarded_keys.iter().copied().collect::<Vec<_>>();
+        for keycode in keycodes {
+            self.forward_key(Keycode::new(keycode), false, time);
+        }
+    }
+
+    /// 按引擎狀態更新預編輯文本和提交，並應用到客戶端。
+    pub(super) fn refresh(&mut self) {
+        let snapshot = self.engine.snapshot();
+        self.update_preedit_panel(&snapshot);
+        if let Some(commit) = snapshot.commit {
+            self.commit_string(commit);
+        }
+        self.input_method.as_ref().unwrap().commit(self.serial);
+        self.record(Action::Commit {
+            serial: self.serial,
+        });
+    }
+
     /// 處理修飾鍵。
     fn handle_modifier(
         &mut self,
-        _serial: u32,
+        serial: u32,
         mods_depressed: u32,
         mods_latched: u32,
         mods_locked: u32,
         group: u32,
     ) {
-        // 更新 XKB 狀態
-        self.state.as_mut().unwrap().update_mask(
-            mods_depressed,
-            mods_latched,
-            mods_locked,
-            0,
-            0,
+        self.record(RecordEvent::Modifiers {
+            serial,
+            depressed: mods_depressed,
+            latched: mods_latched,
+            locked: mods_locked,
             group,
-        );
-        // 更新鍵盤修飾符
-        self.virtual_keyboard.as_ref().unwrap().modifiers(
-            mods_depressed,
-            mods_latched,
-            mods_locked,
-            group,
-        );
+        });
+        // 更新 XKB 狀態
+        if let Some(state) = &mut self.state {
+            state.update_mask(mods_depressed, mods_latched, mods_locked, 0, 0, group);
+        }
+        // 更新鍵盤修飾符，尚未創建時留待創建後發送
+        if self.virtual_keyboard.is_some() {
+            self.forward_modifiers(mods_depressed, mods_latched, mods_locked, group);
+        } else {
+            self.pending_modifiers = Some((mods_depressed, mods_latched, mods_locked, group));
+        }
+    }
+
+    /// 發送修飾鍵狀態到虛擬鍵盤。
+    pub(super) fn forward_modifiers(
+        &mut self,
+        depressed: u32,
+        latched: u32,
+        locked: u32,
+        group: u32,
+    ) {
+        if let Some(virtual_keyboard) = &self.virtual_keyboard {
+            virtual_keyboard.modifiers(depressed, latched, locked, group);
+            self.record(Action::ForwardModifiers {
+                depressed,
+                latched,
+                locked,
+                group,
+            });
+        }
     }
 
     /// 處理重複。
-    fn handle_repeat(&mut self, _rate: i32, _delay: i32) {
+    fn handle_repeat(&mut self, rate: i32, delay: i32) {
+        self.record(RecordEvent::RepeatInfo { rate, delay });
         // TODO: handle repeat
     }
 
-    /// 是否應該更改狀態。
-    fn should_toggle(&mut self, key: Keysym, pressed: bool) -> bool {
-        let prev = self.records[0].replace(key);
-        self.records[1] = prev;
-        return pressed == false
-            && self.records[0] == Some(self.config.switch_key)
-            && self.records[1] == Some(self.config.switch_key);
+    fn is_delete_candidate_key(&self, keysym: Keysym, mods: ModMask) -> bool {
+        self.config
+            .delete_candidate_keys
+            .iter()
+            .any(|binding| binding.matches(keysym, mods))
     }
 
-    /// 更新預編輯文本面板。
-    fn update_preedit_panel(&self) {
-        let mut buf = String::new();
+    /// 從用戶詞典刪除高亮候選，返回是否刪除。
+    fn delete_candidate(&mut self) -> bool {
+        let index = self.engine.context().menu.highlighted_candidate_index;
+        usize::try_from(index).is_ok_and(|index| self.delete_candidate_at(index))
+    }
 
-        // 從 Rime 獲取預編輯文本
-        let preedit = self.engine.preedit();
-        if let Some(text) = preedit.text {
-            buf.push_str(&text);
+    /// 從用戶詞典刪除當前頁第 `index` 個候選，返回是否刪除。
+    pub(super) fn delete_candidate_at(&mut self, index: usize) -> bool {
+        let menu = self.engine.context().menu;
+        let Some(candidate) = menu.candidates.get(index) else {
+            return false;
+        };
+        if !self.engine.delete(index) {
+            return false;
         }
+        info!("Delete candidate: {}", candidate.text);
+        self.notice = Some(format!("✗{}", candidate.text));
+        true
+    }
 
-        // 從 Rime 獲取候選詞
-        let cand = self.engine.candidate();
-        for i in 0..cand.num_candidates {
-            // 編號或者高亮
-            if i == cand.highlighted_candidate_index {
-                buf.push('⁺');
-            } else {
-                buf.push_str(&map_digits(i));
+    /// 按引擎狀態更新候選界面。
+    fn update_preedit_panel(&mut self, snapshot: &Snapshot) {
+        if let Some(input) = &self.codepoint {
+            let mut model = UiModel::default();
+            model.set_text(&input.preedit());
+            if let Some(preedit) = self.renderer.render(&model) {
+                self.set_preedit(preedit);
             }
-            // 候選詞
-            buf.push_str(&self.engine.candidate_get(i).unwrap_or("".to_string()));
+            return;
+        }
+        if self.emoji.is_active() {
+            let model = self.emoji.model();
+            if let Some(preedit) = self.renderer.render(&model) {
+                self.set_preedit(preedit);
+            }
+            return;
+        }
+        // 從 Rime 獲取上下文
+        let mut model = UiModel::new(
+            &snapshot.context,
+            snapshot.status.clone(),
+            self.config.preedit_mode,
+            self.notice.clone(),
+        );
+        if let Some(text) = &self.inline_ascii {
+            let code = snapshot.input.clone().unwrap_or_default();
+            model.set_text(&(code + text));
+        }
+        if let Some(composer) = &self.composer {
+            model.insert_pending(composer.sequence());
+        }
+        if let Some(preedit) = self.renderer.render(&model) {
+            self.set_preedit(preedit);
         }
-
-        // 發送設置請求
-        self.set_preedit_string(buf, preedit.start, preedit.end);
     }
 
-    /// 設置预编辑文本。
-    fn set_preedit_string(&self, text: String, start: i32, end: i32) {
+    /// 設置预编辑文本，客戶端不能顯示選區時隱藏選區。
+    fn set_preedit(&mut self, mut preedit: Preedit) {
+        if !self.config.preedit.selection {
+            preedit.hide_selection();
+        }
+        let Preedit {
+            text,
+            cursor_begin,
+            cursor_end,
+        } = preedit;
         info!("Set preedit string: {}", text);
-        self.input_method
-            .as_ref()
-            .unwrap()
-            .set_preedit_string(text, start, end);
+        self.input_method.as_ref().unwrap().set_preedit_string(
+            text.clone(),
+            cursor_begin,
+            cursor_end,
+        );
+        self.record(Action::SetPreeditString {
+            text,
+            cursor_begin,
+            cursor_end,
+        });
+    }
+
+    /// 上傳 keymap 到虛擬鍵盤。
+    pub(super) fn forward_keymap(&mut self, format: KeymapFormat, fd: BorrowedFd, size: u32) {
+        if let Some(virtual_keyboard) = &self.virtual_keyboard {
+            virtual_keyboard.keymap(format.into(), fd, size);
+            self.record(Action::ForwardKeymap {
+                format: format.into(),
+                size,
+            });
+        }
     }
 
     /// 提交文本。
-    fn commit_string(&self, commit: String) {
+    pub(super) fn commit_string(&mut self, commit: String) {
         info!("Commit string: {}", commit);
-        self.input_method.as_ref().unwrap().commit_string(commit);
+        self.input_method
+            .as_ref()
+            .unwrap()
+            .commit_string(commit.clone());
+        self.record(Action::CommitString { text: Some(commit) });
     }
 }
 
-/// 獲取毫秒時間。
-fn time_ms() -> u32 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u32
-}
-
-/// 映射數位。
-fn map_digits(number: i32) -> String {
-    number
-        .to_string()
-        .chars()
-        .map(|c| match c {
-            '0' => '¹',
-            '1' => '²',
-            '2' => '³',
-            '3' => '⁴',
-            '4' => '⁵',
-            '5' => '⁶',
-            '6' => '⁷',
-            '7' => '⁸',
-            '8' => '⁹',
-            '9' => '⁰',
-            '-' => '⁻',
-            c => c,
-        })
-        .collect::<String>()
+/// 讀取 keymap 文本，不改變文件偏移。
+fn read_keymap(fd: &OwnedFd, size: u32) -> std::io::Result<String> {
+    let mut buf = vec![0; size as usize];
+    File::from(fd.try_clone()?).read_exact_at(&mut buf, 0)?;
+    // 去掉結尾的 NUL
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    Ok(String::from_utf8_lossy(&buf).into_owned())
 }
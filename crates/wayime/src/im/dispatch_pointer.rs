@@ -0,0 +1,186 @@
+use log::info;
+use wayland_client::{
+    protocol::wl_pointer::{Axis, ButtonState, Event, WlPointer},
+    Connection, Dispatch, QueueHandle, WEnum,
+};
+
+use super::Im;
+
+// linux/input-event-codes.h
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// 累計滾動距離達到此值時翻一頁。
+const SCROLL_STEP: f64 = 10.0;
+
+/// 指針在候選框上的狀態。
+#[derive(Debug, Default)]
+pub(super) struct PointerState {
+    /// 指針是否在候選框表面上
+    focused: bool,
+    x: f64,
+    y: f64,
+    /// 懸停的候選
+    hovered: Option<usize>,
+    /// 等待再次右擊確認刪除的候選
+    pending_delete: Option<usize>,
+    /// 未滿一頁的滾動距離
+    scroll: f64,
+}
+
+/// 處理指針事件，只響應候選框表面上的事件。
+impl Dispatch<WlPointer, ()> for Im {
+    fn event(
+        im: &mut Self,
+        _: &WlPointer,
+        event: <WlPointer as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            Event::Enter {
+                surface,
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                im.pointer_state = PointerState {
+                    focused: im
+                        .renderer
+                        .pointer_target()
+                        .is_some_and(|(target, _)| *target == surface),
+                    ..Default::default()
+                };
+                im.handle_pointer_motion(surface_x, surface_y);
+            }
+            Event::Leave { .. } => {
+                im.pointer_state = PointerState::default();
+            }
+            Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                im.handle_pointer_motion(surface_x, surface_y);
+            }
+            Event::Button {
+                button,
+                state: WEnum::Value(ButtonState::Pressed),
+                ..
+            } => {
+                // 點擊打斷切換鍵單擊
+                im.tap.cancel();
+                im.handle_pointer_button(button);
+            }
+            Event::Axis {
+                axis: WEnum::Value(Axis::VerticalScroll),
+                value,
+                ..
+            } => {
+                im.handle_pointer_axis(value);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Im {
+    /// 坐標處的候選頁內序號。
+    fn hit(&self, x: f64, y: f64) -> Option<usize> {
+        let (_, layout) = self.renderer.pointer_target()?;
+        layout.hit(x, y)
+    }
+
+    /// 懸停時高亮候選。
+    fn handle_pointer_motion(&mut self, x: f64, y: f64) {
+        if !self.pointer_state.focused {
+            return;
+        }
+        self.pointer_state.x = x;
+        self.pointer_state.y = y;
+        let hovered = self.hit(x, y);
+        if hovered == self.pointer_state.hovered {
+            return;
+        }
+        self.pointer_state.hovered = hovered;
+        if let Some(index) = hovered {
+            let highlighted = if self.emoji.is_active() {
+                self.emoji.highlight(index)
+            } else {
+                self.engine.highlight(index)
+            };
+            if highlighted {
+                self.refresh();
+            }
+        }
+    }
+
+    /// 左擊選擇候選，右擊兩次刪除候選。
+    fn handle_pointer_button(&mut self, button: u32) {
+        let PointerState { focused, x, y, .. } = self.pointer_state;
+        if !focused {
+            return;
+        }
+        let Some(index) = self.hit(x, y) else {
+            return;
+        };
+        let pending_delete = self.pointer_state.pending_delete.take();
+        self.notice = None;
+        // 表情選擇中只能左擊選擇
+        if self.emoji.is_active() {
+            if button == BTN_LEFT {
+                if let Some(emoji) = self.emoji.select(Some(index)) {
+                    self.commit_string(emoji.to_string());
+                }
+                self.pointer_state.hovered = None;
+                self.refresh();
+            }
+            return;
+        }
+        match button {
+            BTN_LEFT => {
+                info!("Select candidate by pointer: {index}");
+                self.engine.select(index);
+            }
+            BTN_RIGHT if pending_delete == Some(index) => {
+                self.delete_candidate_at(index);
+            }
+            BTN_RIGHT => {
+                if let Some(candidate) = self.engine.context().menu.candidates.get(index) {
+                    self.notice = Some(format!("✗{}?", candidate.text));
+                    self.pointer_state.pending_delete = Some(index);
+                }
+            }
+            _ => return,
+        }
+        // 候選已變化，下次移動時重新高亮
+        self.pointer_state.hovered = None;
+        self.refresh();
+    }
+
+    /// 滾動翻頁，向下爲下一頁。
+    fn handle_pointer_axis(&mut self, value: f64) {
+        let state = &mut self.pointer_state;
+        if !state.focused {
+            return;
+        }
+        state.scroll += value;
+        let mut changed = false;
+        while state.scroll.abs() >= SCROLL_STEP {
+            let backward = state.scroll < 0.0;
+            state.scroll -= SCROLL_STEP.copysign(state.scroll);
+            changed |= if self.emoji.is_active() {
+                self.emoji.change_page(backward)
+            } else {
+                self.engine.change_page(backward)
+            };
+        }
+        if changed {
+            self.pointer_state.hovered = None;
+            self.pointer_state.pending_delete = None;
+            self.notice = None;
+            self.refresh();
+        }
+    }
+}
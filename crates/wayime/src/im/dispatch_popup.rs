@@ -0,0 +1,77 @@
+use wayland_client::{
+    delegate_noop,
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_compositor::WlCompositor,
+        wl_shm::WlShm,
+        wl_shm_pool::WlShmPool,
+        wl_surface::{self, WlSurface},
+    },
+    Connection, Dispatch, QueueHandle,
+};
+use wayland_protocols::wp::{
+    fractional_scale::v1::client::{
+        wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+    },
+    viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+};
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_popup_surface_v2::ZwpInputPopupSurfaceV2;
+
+use super::Im;
+use crate::ui::Scale;
+
+delegate_noop!(super::Im: WlCompositor);
+delegate_noop!(super::Im: WlShmPool);
+delegate_noop!(super::Im: ignore WlShm);
+delegate_noop!(super::Im: ignore ZwpInputPopupSurfaceV2);
+delegate_noop!(super::Im: WpFractionalScaleManagerV1);
+delegate_noop!(super::Im: WpViewporter);
+delegate_noop!(super::Im: WpViewport);
+
+/// 合成器用完後銷毀緩衝區，每次繪製都使用新緩衝區。
+impl Dispatch<WlBuffer, ()> for Im {
+    fn event(
+        _: &mut Self,
+        buffer: &WlBuffer,
+        event: wl_buffer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+        }
+    }
+}
+
+/// 整數縮放，僅在合成器不支持分數縮放時生效。
+impl Dispatch<WlSurface, ()> for Im {
+    fn event(
+        im: &mut Self,
+        _: &WlSurface,
+        event: wl_surface::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_surface::Event::PreferredBufferScale { factor } = event {
+            im.renderer.set_scale(Scale::Integer(factor));
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleV1, ()> for Im {
+    fn event(
+        im: &mut Self,
+        _: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            im.renderer.set_scale(Scale::Fractional(scale));
+        }
+    }
+}
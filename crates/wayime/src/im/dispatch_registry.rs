@@ -1,10 +1,18 @@
+use std::os::fd::AsFd;
+
 use wayland_client::{
     protocol::{
+        wl_compositor::WlCompositor,
         wl_registry::{Event, WlRegistry},
         wl_seat::WlSeat,
+        wl_shm::WlShm,
     },
     Dispatch, QueueHandle,
 };
+use wayland_protocols::wp::{
+    fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    viewporter::client::wp_viewporter::WpViewporter,
+};
 use wayland_protocols_misc::{
     zwp_input_method_v2::client::zwp_input_method_manager_v2::ZwpInputMethodManagerV2,
     zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
@@ -45,14 +53,31 @@ impl Dispatch<WlRegistry, ()> for Im {
                     );
                     im.init_virtual_keyboard(qh);
                 }
+                // 繪製彈出窗口
+                "wl_compositor" => {
+                    im.compositor =
+                        Some(proxy.bind::<WlCompositor, _, _>(name, version.min(6), qh, ()));
+                }
+                "wl_shm" => {
+                    im.shm = Some(proxy.bind::<WlShm, _, _>(name, 1, qh, ()));
+                }
+                "wp_fractional_scale_manager_v1" => {
+                    im.fractional_scale_manager =
+                        Some(proxy.bind::<WpFractionalScaleManagerV1, _, _>(name, 1, qh, ()));
+                }
+                "wp_viewporter" => {
+                    im.viewporter = Some(proxy.bind::<WpViewporter, _, _>(name, 1, qh, ()));
+                }
                 // 其他接口不處理
-                _ => {}
+                _ => return,
             },
             Event::GlobalRemove { .. } => {
                 // TODO: 處理消失情况
+                return;
             }
-            _ => {}
+            _ => return,
         }
+        im.bind_renderer(qh);
     }
 }
 
@@ -73,6 +98,12 @@ impl Im {
             if let (Some(manager), Some(seat)) = (&self.virtual_keyboard_manager, &self.seat) {
                 let virtual_keyboard = manager.create_virtual_keyboard(seat, qh, ());
                 self.virtual_keyboard = Some(virtual_keyboard);
+                if let Some((format, fd, size)) = self.pending_keymap.take() {
+                    self.forward_keymap(format, fd.as_fd(), size);
+                }
+                if let Some((depressed, latched, locked, group)) = self.pending_modifiers.take() {
+                    self.forward_modifiers(depressed, latched, locked, group);
+                }
             }
         }
     }
@@ -1,3 +1,32 @@
-use wayland_client::{delegate_noop, protocol::wl_seat::WlSeat};
+use wayland_client::{
+    protocol::wl_seat::{Capability, Event, WlSeat},
+    Connection, Dispatch, QueueHandle, WEnum,
+};
 
-delegate_noop!(super::Im: ignore WlSeat);
+use super::Im;
+
+/// 處理座位事件，按能力獲取指針。
+impl Dispatch<WlSeat, ()> for Im {
+    fn event(
+        im: &mut Self,
+        seat: &WlSeat,
+        event: <WlSeat as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            let has_pointer = capabilities.contains(Capability::Pointer);
+            if has_pointer && im.pointer.is_none() {
+                im.pointer = Some(seat.get_pointer(qh, ()));
+            } else if !has_pointer {
+                if let Some(pointer) = im.pointer.take() {
+                    pointer.release();
+                }
+            }
+        }
+    }
+}
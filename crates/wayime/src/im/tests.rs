@@ -0,0 +1,866 @@
+use std::time::Duration;
+
+use rime_api::{MockDictionary, Rime};
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::server::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use wayland_server::protocol::wl_keyboard::KeyState;
+
+use self::harness::{Observed, TestHarness};
+use super::Im;
+use crate::{
+    clock::ManualClock,
+    compose::{Composer, TEST_COMPOSE},
+    engine::Engine,
+    layout::{CandidateLayout, LayoutMetrics},
+    preedit::{Preedit, PreeditConfig},
+    record::{self, Action, Record, Recorder},
+    replay::{self, compositor::Compositor},
+    switch::{InlineAsciiConfig, SwitchAction},
+    ui::{CandidateRenderer, InlineRenderer, Renderer, ThemeConfig, UiModel},
+    Config, SchemaConfig,
+};
+
+mod harness;
+
+/// 測試用 keymap.
+const KEYMAP: &str = include_str!("tests/keymap.xkb");
+
+/// 測試用字體，所有字符均爲方框，由 `scripts/test_font.py` 生成。
+const FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/im/tests/box.ttf");
+
+/// keymap.xkb 中的 evdev 鍵碼。
+mod key {
+    pub const ONE: u32 = 2;
+    pub const TWO: u32 = 3;
+    pub const THREE: u32 = 4;
+    pub const FOUR: u32 = 5;
+    pub const ZERO: u32 = 11;
+    pub const DOWN: u32 = 108;
+    pub const DELETE: u32 = 111;
+    pub const ENTER: u32 = 28;
+    pub const BACKSPACE: u32 = 14;
+    pub const EQUAL: u32 = 13;
+    pub const E: u32 = 18;
+    pub const O: u32 = 24;
+    pub const I: u32 = 23;
+    pub const A: u32 = 30;
+    pub const C: u32 = 46;
+    pub const D: u32 = 32;
+    pub const G: u32 = 34;
+    pub const H: u32 = 35;
+    pub const L: u32 = 38;
+    pub const M: u32 = 50;
+    pub const N: u32 = 49;
+    pub const R: u32 = 19;
+    pub const S: u32 = 31;
+    pub const SPACE: u32 = 57;
+    pub const T: u32 = 20;
+    pub const U: u32 = 22;
+    pub const X: u32 = 45;
+    pub const KEYBOARD: u32 = 192;
+}
+
+// linux/input-event-codes.h
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// 在給定表面上按等寬字符佈局候選，預編輯文本同內嵌顯示。
+struct SurfaceRenderer {
+    inline: InlineRenderer,
+    surface: WlSurface,
+    layout: CandidateLayout,
+}
+
+impl CandidateRenderer for SurfaceRenderer {
+    fn render(&mut self, model: &UiModel) -> Option<Preedit> {
+        let preedit = model.composition.preedit.as_deref().unwrap_or_default();
+        let measure = |text: &str| text.chars().count() as f64 * 8.0;
+        self.layout =
+            CandidateLayout::vertical(LayoutMetrics::default(), measure, preedit, &model.rows());
+        self.inline.render(model)
+    }
+
+    fn pointer_target(&self) -> Option<(&WlSurface, &CandidateLayout)> {
+        Some((&self.surface, &self.layout))
+    }
+}
+
+#[test]
+fn emoji_query_matches_chinese_through_rime() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config::default();
+    config.emoji.history = Some(dir.path().join("emoji_recent"));
+    let mut harness = harness_with(im_with(config));
+    harness.tap(key::N);
+    // Control+Alt+e
+    harness.modifiers(12, 0, 0, 0);
+    harness.tap(key::E);
+    harness.modifiers(0, 0, 0, 0);
+    for key in [
+        key::X,
+        key::I,
+        key::O,
+        key::N,
+        key::G,
+        key::M,
+        key::A,
+        key::O,
+    ] {
+        harness.tap(key);
+    }
+    assert!(harness.preedit().unwrap().starts_with(":xiongmao"));
+    harness.tap(key::SPACE);
+    assert_eq!(harness.commit_strings(), ["🐼"]);
+    // 轉換查詢不影響 Rime 中的輸入
+    harness.tap(key::I);
+    assert_eq!(harness.preedit(), Some("ni"));
+}
+
+/// 默認佈局中第 `index` 個候選的中心縱坐標。
+fn candidate_y(index: usize) -> f64 {
+    36.0 + 20.0 * index as f64
+}
+
+const PRESSED: u32 = KeyState::Pressed as u32;
+const RELEASED: u32 = KeyState::Released as u32;
+
+fn im() -> Im {
+    im_with(Config::default())
+}
+
+fn im_with(config: Config) -> Im {
+    let dictionary = MockDictionary::new()
+        .entry("nihao", ["你好", "擬好"])
+        .entry("ma", ["嗎", "媽", "馬", "麻", "罵", "碼"])
+        .entry("xiongmao", ["熊猫"]);
+    let engine = Engine::spawn(|| Ok(Rime::mock(dictionary))).unwrap();
+    Im::with_engine(config, engine)
+}
+
+fn harness() -> TestHarness {
+    harness_with(im())
+}
+
+fn harness_with(im: Im) -> TestHarness {
+    let mut harness = TestHarness::new(im);
+    harness.keymap(KEYMAP);
+    harness.activate();
+    harness
+}
+
+#[test]
+fn nihao_space_commits() {
+    let mut harness = harness();
+    for key in [key::N, key::I, key::H, key::A, key::O] {
+        harness.tap(key);
+    }
+    assert_eq!(harness.preedit(), Some("nihao⁺你好²擬好"));
+    assert!(harness.commit_strings().is_empty());
+
+    harness.tap(key::SPACE);
+    assert_eq!(harness.commit_strings(), ["你好"]);
+    assert_eq!(harness.preedit(), Some(""));
+
+    // 組合中的按下均未轉發
+    assert!(harness
+        .virtual_keys()
+        .iter()
+        .all(|&(_, state)| state != PRESSED));
+    // 提交使用 done 事件的計數
+    assert!(harness
+        .compositor
+        .actions
+        .iter()
+        .all(|action| !matches!(action, Action::Commit { serial } if *serial != 1)));
+}
+
+#[test]
+fn preedit_cursor_covers_composition() {
+    let mut harness = harness();
+    for key in [key::N, key::I, key::H, key::A, key::O] {
+        harness.tap(key);
+    }
+    harness.tap(key::SPACE);
+    let mut cursors = harness
+        .compositor
+        .actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::SetPreeditString {
+                cursor_begin,
+                cursor_end,
+                ..
+            } => Some((*cursor_begin, *cursor_end)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // 鬆開按鍵時重發相同文本
+    cursors.dedup();
+    // 選中整個輸入碼，不含其後的候選；提交後隱藏光標
+    assert_eq!(cursors, [(0, 1), (0, 2), (0, 3), (0, 4), (0, 5), (-1, -1)]);
+}
+
+#[test]
+fn preedit_shows_page_position() {
+    let mut harness = harness();
+    harness.tap(key::M);
+    harness.tap(key::A);
+    assert_eq!(harness.preedit(), Some("ma⁺嗎²媽³馬⁴麻⁵罵 1/…"));
+    harness.tap(key::EQUAL);
+    assert_eq!(harness.preedit(), Some("ma⁺碼 2/2"));
+}
+
+#[test]
+fn shift_delete_removes_highlighted_candidate() {
+    let mut harness = harness();
+    for key in [key::N, key::I, key::H, key::A, key::O, key::DOWN] {
+        harness.tap(key);
+    }
+    assert_eq!(harness.preedit(), Some("nihao¹你好⁺擬好"));
+
+    harness.modifiers(1, 0, 0, 0);
+    harness.tap(key::DELETE);
+    harness.modifiers(0, 0, 0, 0);
+    assert_eq!(harness.preedit(), Some("nihao⁺你好 ✗擬好"));
+    assert!(harness.virtual_keys().is_empty());
+
+    // 下次按鍵清除提示
+    harness.tap(key::DOWN);
+    assert_eq!(harness.preedit(), Some("nihao⁺你好"));
+}
+
+#[test]
+fn pointer_on_candidate_popup() {
+    let mut harness = harness();
+    let surface = harness.create_surface();
+    harness.im.set_renderer(SurfaceRenderer {
+        inline: InlineRenderer::new(PreeditConfig::default()),
+        surface,
+        layout: CandidateLayout::default(),
+    });
+    harness.tap(key::M);
+    harness.tap(key::A);
+
+    // 懸停高亮
+    harness.pointer_enter(10.0, candidate_y(1));
+    assert_eq!(harness.preedit(), Some("ma¹嗎⁺媽³馬⁴麻⁵罵 1/…"));
+
+    // 滾動翻頁，不足一步時不翻
+    harness.scroll(5.0);
+    assert_eq!(harness.preedit(), Some("ma¹嗎⁺媽³馬⁴麻⁵罵 1/…"));
+    harness.scroll(5.0);
+    assert_eq!(harness.preedit(), Some("ma⁺碼 2/2"));
+    harness.scroll(-10.0);
+    assert_eq!(harness.preedit(), Some("ma⁺嗎²媽³馬⁴麻⁵罵 1/…"));
+
+    // 右擊兩次刪除
+    harness.click(BTN_RIGHT);
+    assert_eq!(harness.preedit(), Some("ma⁺嗎²媽³馬⁴麻⁵罵 ✗媽? 1/…"));
+    harness.click(BTN_RIGHT);
+    assert_eq!(harness.preedit(), Some("ma⁺嗎²馬³麻⁴罵⁵碼 ✗媽"));
+
+    // 左擊選擇
+    harness.pointer_motion(10.0, candidate_y(2));
+    harness.click(BTN_LEFT);
+    assert_eq!(harness.commit_strings(), ["麻"]);
+    assert!(harness.virtual_keys().is_empty());
+}
+
+#[test]
+fn pointer_outside_popup_is_ignored() {
+    let mut harness = harness();
+    harness.create_surface();
+    harness.tap(key::M);
+    harness.tap(key::A);
+    harness.pointer_enter(10.0, candidate_y(1));
+    harness.click(BTN_LEFT);
+    assert_eq!(harness.preedit(), Some("ma⁺嗎²媽³馬⁴麻⁵罵 1/…"));
+    assert!(harness.commit_strings().is_empty());
+}
+
+#[test]
+fn popup_draws_candidates() {
+    let config = Config {
+        renderer: Renderer::Popup,
+        theme: ThemeConfig {
+            font_file: Some(FONT.into()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut harness = harness_with(im_with(config));
+    harness.tap(key::M);
+    harness.tap(key::A);
+    // 預編輯文本只含輸入碼，候選畫在彈出窗口中
+    assert_eq!(harness.preedit(), Some("ma"));
+    let (width, height) = harness.attached().unwrap();
+    assert!(width > 0);
+    // 組合文本一行，候選五行
+    assert_eq!(height, 6 + 20 * 6 + 6);
+
+    harness.tap(key::SPACE);
+    assert_eq!(harness.commit_strings(), ["嗎"]);
+    assert_eq!(harness.attached(), None);
+}
+
+#[test]
+fn null_renderer_sets_no_preedit() {
+    let config = Config {
+        renderer: Renderer::None,
+        ..Default::default()
+    };
+    let mut harness = harness_with(im_with(config));
+    for key in [key::N, key::I, key::H, key::A, key::O, key::SPACE] {
+        harness.tap(key);
+    }
+    assert_eq!(harness.preedit(), None);
+    assert_eq!(harness.commit_strings(), ["你好"]);
+}
+
+#[test]
+fn selection_hidden_for_client() {
+    let cursor = |harness: &mut TestHarness| {
+        harness
+            .take_actions()
+            .into_iter()
+            .rev()
+            .find_map(|action| match action {
+                Action::SetPreeditString {
+                    cursor_begin,
+                    cursor_end,
+                    ..
+                } => Some((cursor_begin, cursor_end)),
+                _ => None,
+            })
+    };
+    let mut harness = harness();
+    harness.tap(key::M);
+    harness.tap(key::A);
+    assert_eq!(cursor(&mut harness), Some((0, 2)));
+
+    let mut config = Config::default();
+    config.preedit.selection = false;
+    let mut harness = harness_with(im_with(config));
+    harness.tap(key::M);
+    harness.tap(key::A);
+    assert_eq!(cursor(&mut harness), Some((-1, -1)));
+}
+
+#[test]
+fn bypass_forwards_keys_untouched() {
+    let mut harness = harness();
+    // 切換到 ASCII 模式
+    harness.tap(key::KEYBOARD);
+    harness.take_actions();
+
+    for key in [key::H, key::I, key::ONE] {
+        harness.tap(key);
+    }
+    harness.modifiers(1, 0, 0, 0);
+    assert_eq!(
+        harness.take_actions(),
+        [
+            Action::ForwardKey {
+                key: key::H,
+                state: PRESSED
+            },
+            Action::ForwardKey {
+                key: key::H,
+                state: RELEASED
+            },
+            Action::ForwardKey {
+                key: key::I,
+                state: PRESSED
+            },
+            Action::ForwardKey {
+                key: key::I,
+                state: RELEASED
+            },
+            Action::ForwardKey {
+                key: key::ONE,
+                state: PRESSED
+            },
+            Action::ForwardKey {
+                key: key::ONE,
+                state: RELEASED
+            },
+            Action::ForwardModifiers {
+                depressed: 1,
+                latched: 0,
+                locked: 0,
+                group: 0
+            },
+        ]
+    );
+}
+
+#[test]
+fn same_keymap_is_forwarded_once() {
+    let mut harness = harness();
+    harness.keymap(KEYMAP);
+    // 另一 keymap 仍然上傳
+    harness.keymap(&format!("{KEYMAP}\n"));
+    let keymaps = harness
+        .compositor
+        .actions
+        .iter()
+        .filter(|action| matches!(action, Action::ForwardKeymap { .. }))
+        .count();
+    assert_eq!(keymaps, 2);
+}
+
+#[test]
+fn keymap_waits_for_virtual_keyboard() {
+    let mut harness = harness();
+    // 虛擬鍵盤尚未創建時收到新 keymap
+    harness.im.virtual_keyboard.take().unwrap().destroy();
+    harness.take_actions();
+    harness.keymap(&format!("{KEYMAP}\n"));
+    let forwarded = |harness: &TestHarness| {
+        harness
+            .compositor
+            .actions
+            .iter()
+            .filter(|action| matches!(action, Action::ForwardKeymap { .. }))
+            .count()
+    };
+    assert_eq!(forwarded(&harness), 0);
+
+    // 創建後上傳，此後同一 keymap 仍然跳過
+    harness
+        .display
+        .handle()
+        .create_global::<Compositor<Observed>, ZwpVirtualKeyboardManagerV1, _>(1, ());
+    harness.dispatch();
+    assert_eq!(forwarded(&harness), 1);
+    harness.keymap(&format!("{KEYMAP}\n"));
+    assert_eq!(forwarded(&harness), 1);
+}
+
+#[test]
+fn input_before_virtual_keyboard() {
+    let mut harness = harness();
+    harness.im.virtual_keyboard.take().unwrap().destroy();
+    harness.take_actions();
+    // 鎖定 Lock, 未組合時 Down 原樣轉發
+    harness.modifiers(0, 0, 2, 0);
+    harness.tap(key::DOWN);
+    assert!(harness.take_actions().iter().all(|action| !matches!(
+        action,
+        Action::ForwardKey { .. } | Action::ForwardModifiers { .. }
+    )));
+
+    // 創建後補發修飾鍵
+    harness
+        .display
+        .handle()
+        .create_global::<Compositor<Observed>, ZwpVirtualKeyboardManagerV1, _>(1, ());
+    harness.dispatch();
+    assert_eq!(
+        harness.take_actions(),
+        [Action::ForwardModifiers {
+            depressed: 0,
+            latched: 0,
+            locked: 2,
+            group: 0
+        }]
+    );
+    harness.tap(key::DOWN);
+    assert_eq!(
+        harness.virtual_keys(),
+        [(key::DOWN, PRESSED), (key::DOWN, RELEASED)]
+    );
+}
+
+#[test]
+fn keymap_swap_keeps_locked_modifiers() {
+    let mut harness = harness();
+    // 鎖定 Lock
+    harness.modifiers(0, 0, 2, 0);
+    harness.keymap(&format!("{KEYMAP}\n"));
+    let state = harness.im.state.as_ref().unwrap();
+    assert!(state.mod_name_is_active("Lock", xkbcommon::xkb::STATE_MODS_LOCKED));
+}
+
+#[test]
+fn invalid_keymap_forwards_keys_untouched() {
+    let mut harness = harness();
+    harness.keymap("not a keymap");
+    harness.take_actions();
+    harness.tap(key::N);
+    assert_eq!(
+        harness.virtual_keys(),
+        [(key::N, PRESSED), (key::N, RELEASED)]
+    );
+    assert_eq!(harness.preedit(), None);
+
+    // 收到可用的 keymap 後恢復
+    harness.keymap(KEYMAP);
+    harness.tap(key::N);
+    assert!(harness.preedit().is_some_and(|text| text.starts_with('n')));
+}
+
+#[test]
+fn dead_key_composes_before_rime() {
+    let mut harness = harness();
+    harness.im.composer = Composer::from_buffer(&harness.im.context, TEST_COMPOSE);
+    assert!(harness.im.composer.is_some());
+    harness.keymap(&KEYMAP.replace("[ equal, plus ]", "[ dead_acute, plus ]"));
+    harness.tap(key::EQUAL);
+    assert_eq!(harness.preedit(), Some("´"));
+    // Rime 不處理 é, 直接提交
+    harness.tap(key::E);
+    assert_eq!(harness.commit_strings(), ["é"]);
+    assert_eq!(harness.preedit(), Some(""));
+    assert!(harness.virtual_keys().is_empty());
+}
+
+#[test]
+fn forwarded_keys_are_released_once() {
+    let mut harness = harness();
+    harness.tap(key::KEYBOARD);
+    harness.take_actions();
+
+    // 轉發的按下在切換模式時鬆開，之後不再轉發
+    harness.key(key::H, KeyState::Pressed);
+    harness.tap(key::KEYBOARD);
+    harness.key(key::H, KeyState::Released);
+    // 組合中的按下在切換模式後不轉發鬆開
+    harness.key(key::N, KeyState::Pressed);
+    harness.tap(key::KEYBOARD);
+    harness.key(key::N, KeyState::Released);
+    // 停用時補發鬆開
+    harness.key(key::I, KeyState::Pressed);
+    harness.send(&record::Event::Deactivate);
+    assert_eq!(
+        harness.virtual_keys(),
+        [
+            (key::H, PRESSED),
+            (key::KEYBOARD, PRESSED),
+            (key::H, RELEASED),
+            (key::KEYBOARD, RELEASED),
+            (key::I, PRESSED),
+            (key::I, RELEASED),
+        ]
+    );
+}
+
+#[test]
+fn forwarded_keys_released_when_unavailable() {
+    let mut harness = harness();
+    harness.tap(key::KEYBOARD);
+    harness.take_actions();
+    harness.key(key::H, KeyState::Pressed);
+    harness.send(&record::Event::Unavailable);
+    assert_eq!(
+        harness.virtual_keys(),
+        [(key::H, PRESSED), (key::H, RELEASED)]
+    );
+}
+
+#[test]
+fn forwarded_keys_keep_event_time() {
+    let clock = ManualClock::default();
+    let mut im = im();
+    im.set_clock(clock.clone());
+    let mut harness = harness_with(im);
+    harness.tap(key::KEYBOARD);
+
+    // 合成器時間每個事件加 10, 切換鍵單擊鬆開時才確定，故同樣轉發
+    harness.tap(key::H);
+    harness.key(key::I, KeyState::Pressed);
+    // 停用時補發的鬆開按最近的時間戳推算
+    clock.advance(Duration::from_millis(25));
+    harness.send(&record::Event::Deactivate);
+    assert_eq!(harness.virtual_key_times(), [10, 20, 30, 40, 50, 75]);
+}
+
+#[test]
+fn switch_key_needs_clean_tap() {
+    let mut harness = harness();
+    // 按住過久
+    harness.key(key::KEYBOARD, KeyState::Pressed);
+    harness.wait(600);
+    harness.key(key::KEYBOARD, KeyState::Released);
+    // 其間按了其他鍵
+    harness.key(key::KEYBOARD, KeyState::Pressed);
+    harness.tap(key::N);
+    harness.key(key::KEYBOARD, KeyState::Released);
+    assert_eq!(harness.preedit(), Some("n"));
+    harness.send(&record::Event::Deactivate);
+    harness.activate();
+    // 按住其他修飾鍵
+    harness.modifiers(4, 0, 0, 0);
+    harness.tap(key::KEYBOARD);
+    harness.modifiers(0, 0, 0, 0);
+    harness.take_actions();
+    // 仍在中文模式
+    harness.tap(key::N);
+    assert_eq!(harness.preedit(), Some("n"));
+    assert!(harness.virtual_keys().is_empty());
+}
+
+#[test]
+fn switch_key_commits_code() {
+    let mut harness = harness();
+    harness.tap(key::N);
+    harness.tap(key::I);
+    harness.tap(key::KEYBOARD);
+    assert_eq!(harness.commit_strings(), ["ni"]);
+    assert_eq!(harness.preedit(), Some(""));
+    // 已切換到英文
+    harness.tap(key::H);
+    assert_eq!(
+        harness.virtual_keys(),
+        [(key::H, PRESSED), (key::H, RELEASED)]
+    );
+}
+
+#[test]
+fn switch_key_inline_ascii() {
+    let config = Config {
+        switch_action: SwitchAction::InlineAscii,
+        ..Config::default()
+    };
+    let mut harness = harness_with(im_with(config));
+    harness.tap(key::N);
+    harness.tap(key::I);
+    harness.tap(key::KEYBOARD);
+    harness.tap(key::H);
+    harness.tap(key::A);
+    assert_eq!(harness.preedit(), Some("niha"));
+    harness.tap(key::ENTER);
+    assert_eq!(harness.commit_strings(), ["niha"]);
+    assert!(harness.virtual_keys().is_empty());
+    // 回到中文
+    harness.tap(key::N);
+    assert_eq!(harness.preedit(), Some("n"));
+}
+
+#[test]
+fn shift_letter_starts_inline_ascii() {
+    let mut harness = harness();
+    harness.tap(key::N);
+    harness.tap(key::I);
+    harness.modifiers(1, 0, 0, 0);
+    harness.tap(key::H);
+    harness.modifiers(0, 0, 0, 0);
+    harness.tap(key::A);
+    assert_eq!(harness.preedit(), Some("niHa"));
+    harness.tap(key::SPACE);
+    assert_eq!(harness.commit_strings(), ["niHa"]);
+    // 回到中文
+    harness.tap(key::N);
+    assert_eq!(harness.preedit(), Some("n"));
+    assert!(harness.virtual_keys().is_empty());
+}
+
+#[test]
+fn schema_prefix_starts_inline_ascii() {
+    let inline_ascii = InlineAsciiConfig {
+        prefixes: vec!["m".to_string()],
+        ..InlineAsciiConfig::default()
+    };
+    let schema = SchemaConfig {
+        inline_ascii: Some(inline_ascii),
+    };
+    let config = Config {
+        schemas: [("mock".to_string(), schema)].into(),
+        ..Config::default()
+    };
+    let mut harness = harness_with(im_with(config));
+    // 前綴不上屏
+    harness.tap(key::M);
+    harness.tap(key::H);
+    harness.tap(key::I);
+    assert_eq!(harness.preedit(), Some("hi"));
+    harness.tap(key::ENTER);
+    assert_eq!(harness.commit_strings(), ["hi"]);
+}
+
+#[test]
+fn caps_lock_bypasses_rime() {
+    let mut harness = harness();
+    harness.tap(key::N);
+    harness.modifiers(0, 0, 2, 0);
+    // 上屏輸入碼後轉發
+    harness.tap(key::H);
+    assert_eq!(harness.commit_strings(), ["n"]);
+    assert_eq!(
+        harness.virtual_keys(),
+        [(key::H, PRESSED), (key::H, RELEASED)]
+    );
+}
+
+#[test]
+fn emoji_picker_commits_and_remembers() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config::default();
+    config.emoji.history = Some(dir.path().join("emoji_recent"));
+    let mut harness = harness_with(im_with(config));
+    let pick = |harness: &mut TestHarness, select: u32| {
+        // Control+Alt+e
+        harness.modifiers(12, 0, 0, 0);
+        harness.tap(key::E);
+        harness.modifiers(0, 0, 0, 0);
+        for key in [key::C, key::A, key::T] {
+            harness.tap(key);
+        }
+        assert!(harness.preedit().unwrap().starts_with(":cat"));
+        harness.tap(select);
+    };
+    pick(&mut harness, key::THREE);
+    // 最近使用的排在前面
+    pick(&mut harness, key::SPACE);
+    assert_eq!(harness.commit_strings(), ["🐈", "🐈"]);
+    assert!(harness.virtual_keys().is_empty());
+    // 退出後回到 Rime
+    harness.tap(key::N);
+    assert_eq!(harness.preedit(), Some("n"));
+}
+
+#[test]
+fn emoji_query_reaches_cjk_symbol() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config::default();
+    config.emoji.history = Some(dir.path().join("emoji_recent"));
+    let mut harness = harness_with(im_with(config));
+    // Control+Alt+e
+    harness.modifiers(12, 0, 0, 0);
+    harness.tap(key::E);
+    harness.modifiers(0, 0, 0, 0);
+    for key in [
+        key::C,
+        key::O,
+        key::N,
+        key::G,
+        key::R,
+        key::A,
+        key::T,
+        key::U,
+        key::L,
+        key::A,
+        key::T,
+        key::I,
+        key::O,
+        key::N,
+        key::S,
+    ] {
+        harness.tap(key);
+    }
+    // 只有一個結果，沒有對應候選的數字追加到查詢
+    harness.tap(key::TWO);
+    assert!(harness.preedit().unwrap().starts_with(":congratulations2"));
+    harness.tap(key::BACKSPACE);
+    harness.tap(key::ONE);
+    assert_eq!(harness.commit_strings(), ["㊗️"]);
+}
+
+#[test]
+fn codepoint_prefix_while_composing() {
+    let mut harness = harness();
+    harness.tap(key::U);
+    // Shift+= 爲 +
+    harness.modifiers(1, 0, 0, 0);
+    harness.tap(key::EQUAL);
+    harness.modifiers(0, 0, 0, 0);
+    for key in [key::FOUR, key::E, key::TWO, key::D] {
+        harness.tap(key);
+    }
+    assert_eq!(
+        harness.preedit(),
+        Some("U+4E2D 中 CJK UNIFIED IDEOGRAPH-4E2D")
+    );
+    harness.tap(key::ENTER);
+    assert_eq!(harness.commit_strings(), ["中"]);
+    assert!(harness.virtual_keys().is_empty());
+}
+
+#[test]
+fn codepoint_key_works_in_bypass() {
+    let mut harness = harness();
+    harness.tap(key::KEYBOARD);
+    // Control+Shift+U
+    harness.modifiers(5, 0, 0, 0);
+    harness.tap(key::U);
+    harness.modifiers(0, 0, 0, 0);
+    harness.tap(key::FOUR);
+    harness.tap(key::ONE);
+    assert_eq!(harness.preedit(), Some("U+41 A LATIN CAPITAL LETTER A"));
+    harness.tap(key::SPACE);
+    assert_eq!(harness.commit_strings(), ["A"]);
+    assert_eq!(
+        harness.virtual_keys(),
+        [(key::KEYBOARD, PRESSED), (key::KEYBOARD, RELEASED)]
+    );
+}
+
+#[test]
+fn codepoint_rejects_nul() {
+    let mut harness = harness();
+    // Control+Shift+U
+    harness.modifiers(5, 0, 0, 0);
+    harness.tap(key::U);
+    harness.modifiers(0, 0, 0, 0);
+    harness.tap(key::ZERO);
+    harness.tap(key::ENTER);
+    // 不提交，繼續顯示錯誤
+    assert!(harness.commit_strings().is_empty());
+    assert_eq!(harness.preedit(), Some("U+0 ✗"));
+    harness.tap(key::ONE);
+    assert_eq!(harness.preedit(), Some("U+01 ✗"));
+}
+
+#[test]
+fn default_recording_hides_text() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let mut recording = im();
+    recording.set_recorder(Recorder::create(file.path(), false).unwrap());
+    let mut harness = harness_with(recording);
+    for key in [key::N, key::I, key::H, key::A, key::O, key::SPACE] {
+        harness.tap(key);
+    }
+    assert_eq!(harness.commit_strings(), ["你好"]);
+    drop(harness);
+
+    // 候選和提交都不寫入記錄，keymap 原文除外
+    let text = record::load(file.path())
+        .unwrap()
+        .into_iter()
+        .filter(|entry| !matches!(entry.record, Record::Event(record::Event::Keymap { .. })))
+        .map(|entry| serde_json::to_string(&entry).unwrap())
+        .collect::<String>();
+    assert!(!text.contains("nihao"));
+    assert!(!text.chars().any(|c| ('\u{4E00}'..='\u{9FFF}').contains(&c)));
+}
+
+#[test]
+fn record_and_replay() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let mut recording = im();
+    recording.set_recorder(Recorder::create(file.path(), false).unwrap());
+    let mut harness = harness_with(recording);
+    for key in [key::N, key::I, key::H, key::A, key::O, key::SPACE] {
+        harness.tap(key);
+    }
+    harness.tap(key::ONE);
+    drop(harness);
+
+    // 默認隱去提交文本
+    let entries = record::load(file.path()).unwrap();
+    assert!(entries
+        .iter()
+        .any(|entry| entry.record == Record::Action(Action::CommitString { text: None })));
+
+    // keymap 和激活事件均來自記錄，重放使用同樣的模擬詞典
+    let dictionary = replay::Dictionary {
+        entries: [(
+            "nihao".to_string(),
+            vec!["你好".to_string(), "擬好".to_string()],
+        )]
+        .into(),
+        ..Default::default()
+    };
+    let im = replay::im(Config::default(), dictionary).unwrap();
+    assert!(replay::replay(im, file.path()).unwrap());
+}
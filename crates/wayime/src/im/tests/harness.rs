@@ -0,0 +1,288 @@
+use std::ops::{Deref, DerefMut};
+
+use wayland_server::protocol::{
+    wl_keyboard::{KeyState, KeymapFormat},
+    wl_pointer::{self, WlPointer},
+    wl_surface::WlSurface,
+};
+
+use crate::{
+    im::Im,
+    record::{Action, Event},
+    replay::{compositor::Observer, Harness},
+};
+
+/// 測試所需的合成器端對象和請求。
+#[derive(Default)]
+pub struct Observed {
+    pointer: Option<WlPointer>,
+    /// 最後創建的表面
+    surface: Option<WlSurface>,
+    /// 最後附加的緩衝區大小
+    attached: Option<(i32, i32)>,
+    key_times: Vec<u32>,
+}
+
+impl Observer for Observed {
+    fn pointer(&mut self, pointer: &WlPointer) {
+        self.pointer = Some(pointer.clone());
+    }
+
+    fn surface(&mut self, surface: &WlSurface) {
+        self.surface = Some(surface.clone());
+    }
+
+    fn attach(&mut self, size: Option<(i32, i32)>) {
+        self.attached = size;
+    }
+
+    fn forward_key(&mut self, time: u32) {
+        self.key_times.push(time);
+    }
+}
+
+/// 測試用的 [`Harness`], 自行生成事件序號和時間。
+pub struct TestHarness {
+    harness: Harness<Observed>,
+    /// 事件序號
+    serial: u32,
+    /// 事件時間
+    time: u32,
+}
+
+impl Deref for TestHarness {
+    type Target = Harness<Observed>;
+
+    fn deref(&self) -> &Harness<Observed> {
+        &self.harness
+    }
+}
+
+impl DerefMut for TestHarness {
+    fn deref_mut(&mut self) -> &mut Harness<Observed> {
+        &mut self.harness
+    }
+}
+
+impl TestHarness {
+    pub fn new(im: Im) -> Self {
+        Self {
+            harness: Harness::with_observer(im, Observed::default()).unwrap(),
+            serial: 0,
+            time: 0,
+        }
+    }
+
+    fn next_serial(&mut self) -> u32 {
+        self.serial += 1;
+        self.serial
+    }
+
+    fn next_time(&mut self) -> u32 {
+        self.time += 10;
+        self.time
+    }
+
+    pub fn dispatch(&mut self) {
+        self.harness.dispatch().unwrap();
+    }
+
+    pub fn send(&mut self, event: &Event) {
+        self.harness.send(event).unwrap();
+    }
+
+    /// 發送 keymap.
+    pub fn keymap(&mut self, text: &str) {
+        self.send(&Event::Keymap {
+            format: KeymapFormat::XkbV1.into(),
+            hash: String::new(),
+            text: text.to_string(),
+        });
+    }
+
+    /// 激活輸入法。
+    pub fn activate(&mut self) {
+        self.send(&Event::Activate);
+        self.send(&Event::Done);
+    }
+
+    /// 發送按鍵事件，`key` 爲 evdev 鍵碼。
+    pub fn key(&mut self, key: u32, state: KeyState) {
+        let serial = self.next_serial();
+        let time = self.next_time();
+        self.send(&Event::Key {
+            serial,
+            time,
+            key,
+            state: state.into(),
+        });
+    }
+
+    /// 按下並鬆開。
+    pub fn tap(&mut self, key: u32) {
+        self.key(key, KeyState::Pressed);
+        self.key(key, KeyState::Released);
+    }
+
+    /// 推進合成器時間，如模擬按住按鍵。
+    pub fn wait(&mut self, ms: u32) {
+        self.time += ms;
+    }
+
+    /// 發送修飾鍵事件。
+    pub fn modifiers(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        let serial = self.next_serial();
+        self.send(&Event::Modifiers {
+            serial,
+            depressed,
+            latched,
+            locked,
+            group,
+        });
+    }
+
+    /// 在另一隊列上創建表面，返回客戶端對象，服務端對象記在 [`Observed`] 中。
+    pub fn create_surface(&mut self) -> probe::WlSurface {
+        let mut probe = probe::Probe::default();
+        let mut queue = self.conn.new_event_queue();
+        self.conn.display().get_registry(&queue.handle(), ());
+        self.dispatch();
+        queue.dispatch_pending(&mut probe).unwrap();
+        let surface = probe
+            .compositor
+            .expect("no wl_compositor global")
+            .create_surface(&queue.handle(), ());
+        self.dispatch();
+        surface
+    }
+
+    /// 指針進入最後創建的表面。
+    pub fn pointer_enter(&mut self, x: f64, y: f64) {
+        let serial = self.next_serial();
+        let surface = self.compositor.observer.surface.as_ref().unwrap();
+        let pointer = self.compositor.observer.pointer.as_ref().unwrap();
+        pointer.enter(serial, surface, x, y);
+        pointer.frame();
+        self.dispatch();
+    }
+
+    pub fn pointer_motion(&mut self, x: f64, y: f64) {
+        let time = self.next_time();
+        let pointer = self.compositor.observer.pointer.as_ref().unwrap();
+        pointer.motion(time, x, y);
+        pointer.frame();
+        self.dispatch();
+    }
+
+    /// 按下並鬆開鼠標按鍵。
+    pub fn click(&mut self, button: u32) {
+        for state in [
+            wl_pointer::ButtonState::Pressed,
+            wl_pointer::ButtonState::Released,
+        ] {
+            let serial = self.next_serial();
+            let time = self.next_time();
+            let pointer = self.compositor.observer.pointer.as_ref().unwrap();
+            pointer.button(serial, time, button, state);
+            pointer.frame();
+        }
+        self.dispatch();
+    }
+
+    /// 縱向滾動，正值向下。
+    pub fn scroll(&mut self, value: f64) {
+        let time = self.next_time();
+        let pointer = self.compositor.observer.pointer.as_ref().unwrap();
+        pointer.axis(time, wl_pointer::Axis::VerticalScroll, value);
+        pointer.frame();
+        self.dispatch();
+    }
+
+    /// 已提交的文本。
+    pub fn commit_strings(&self) -> Vec<&str> {
+        self.compositor
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::CommitString { text } => text.as_deref(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 最後一次設置的預編輯文本。
+    pub fn preedit(&self) -> Option<&str> {
+        self.compositor
+            .actions
+            .iter()
+            .rev()
+            .find_map(|action| match action {
+                Action::SetPreeditString { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+    }
+
+    /// 轉發按鍵的時間戳。
+    pub fn virtual_key_times(&self) -> &[u32] {
+        &self.compositor.observer.key_times
+    }
+
+    /// 最後附加的緩衝區大小。
+    pub fn attached(&self) -> Option<(i32, i32)> {
+        self.compositor.observer.attached
+    }
+
+    /// 轉發給虛擬鍵盤的按鍵。
+    pub fn virtual_keys(&self) -> Vec<(u32, u32)> {
+        self.compositor
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::ForwardKey { key, state } => Some((*key, *state)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// 綁定 `wl_compositor` 以創建表面的客戶端狀態。
+mod probe {
+    use wayland_client::{
+        delegate_noop,
+        protocol::{
+            wl_compositor::WlCompositor,
+            wl_registry::{Event, WlRegistry},
+        },
+        Connection, Dispatch, QueueHandle,
+    };
+
+    pub use wayland_client::protocol::wl_surface::WlSurface;
+
+    #[derive(Default)]
+    pub struct Probe {
+        pub compositor: Option<WlCompositor>,
+    }
+
+    impl Dispatch<WlRegistry, ()> for Probe {
+        fn event(
+            probe: &mut Self,
+            registry: &WlRegistry,
+            event: Event,
+            _: &(),
+            _: &Connection,
+            qh: &QueueHandle<Self>,
+        ) {
+            if let Event::Global {
+                name, interface, ..
+            } = event
+            {
+                if interface == "wl_compositor" {
+                    probe.compositor = Some(registry.bind(name, 4, qh, ()));
+                }
+            }
+        }
+    }
+
+    delegate_noop!(Probe: WlCompositor);
+    delegate_noop!(Probe: ignore WlSurface);
+}
@@ -0,0 +1,244 @@
+use log::warn;
+use serde::Deserialize;
+use xkbcommon::xkb::{
+    self,
+    ffi::{
+        XKB_STATE_LAYOUT_DEPRESSED, XKB_STATE_LAYOUT_LATCHED, XKB_STATE_LAYOUT_LOCKED,
+        XKB_STATE_MODS_DEPRESSED, XKB_STATE_MODS_LATCHED, XKB_STATE_MODS_LOCKED,
+    },
+    Keycode, Keysym, ModMask, StateComponent, KEYMAP_COMPILE_NO_FLAGS, MOD_INVALID,
+};
+
+/// 按鍵如何轉換爲送給 Rime 的 keysym.
+///
+/// ```toml
+/// key-mapping = "physical-us"
+/// # 或固定使用第一組佈局
+/// key-mapping = { group = 1 }
+/// ```
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyMapping {
+    /// 使用當前佈局
+    #[default]
+    AsTyped,
+    /// 按鍵位在 `us` 佈局中的符號
+    PhysicalUs,
+    /// 使用第 N 組佈局，從 1 開始
+    Group(u32),
+}
+
+/// 按 [`KeyMapping`] 將鍵碼轉換爲 keysym.
+pub struct KeyResolver {
+    mapping: KeyMapping,
+    /// `physical-us` 的參照狀態，修飾鍵與實際狀態同步
+    us: Option<xkb::State>,
+    /// `group` 鎖定到該組的狀態，keymap 變化時重建
+    forced: Option<xkb::State>,
+}
+
+impl KeyResolver {
+    /// 編譯 `us` keymap 失敗時退回當前佈局。
+    pub fn new(context: &xkb::Context, mapping: KeyMapping) -> Self {
+        let us = match mapping {
+            KeyMapping::PhysicalUs => {
+                let keymap = xkb::Keymap::new_from_names(
+                    context,
+                    "",
+                    "",
+                    "us",
+                    "",
+                    None,
+                    KEYMAP_COMPILE_NO_FLAGS,
+                );
+                if keymap.is_none() {
+                    warn!("Fail to compile `us` keymap, use the active layout");
+                }
+                keymap.map(|keymap| xkb::State::new(&keymap))
+            }
+            _ => None,
+        };
+        Self {
+            mapping,
+            us,
+            forced: None,
+        }
+    }
+
+    /// 返回鍵碼對應的 keysym, 參照佈局中沒有該鍵時使用當前佈局。
+    ///
+    /// 一個鍵可對應多個 keysym, 此時依次返回。
+    pub fn keysyms(&mut self, state: &xkb::State, keycode: Keycode) -> Vec<Keysym> {
+        let keysym = match self.mapping {
+            KeyMapping::AsTyped => None,
+            KeyMapping::PhysicalUs => self.us.as_mut().map(|us| {
+                let keymap = us.get_keymap();
+                us.update_mask(
+                    remap_mods(state, &keymap, XKB_STATE_MODS_DEPRESSED),
+                    remap_mods(state, &keymap, XKB_STATE_MODS_LATCHED),
+                    remap_mods(state, &keymap, XKB_STATE_MODS_LOCKED),
+                    0,
+                    0,
+                    0,
+                );
+                us.key_get_one_sym(keycode)
+            }),
+            KeyMapping::Group(group) => {
+                let keymap = state.get_keymap();
+                let layout = group.saturating_sub(1);
+                (layout < keymap.num_layouts_for_key(keycode)).then(|| {
+                    // 鎖定到該組，修飾鍵同步，大寫鎖定等轉換與其他選項一致
+                    if self.forced.as_ref().is_some_and(|forced| {
+                        forced.get_keymap().get_raw_ptr() != keymap.get_raw_ptr()
+                    }) {
+                        self.forced = None;
+                    }
+                    let forced = self.forced.get_or_insert_with(|| xkb::State::new(&keymap));
+                    forced.update_mask(
+                        state.serialize_mods(XKB_STATE_MODS_DEPRESSED),
+                        state.serialize_mods(XKB_STATE_MODS_LATCHED),
+                        state.serialize_mods(XKB_STATE_MODS_LOCKED),
+                        0,
+                        0,
+                        layout,
+                    );
+                    forced.key_get_one_sym(keycode)
+                })
+            }
+        };
+        match keysym.filter(|&keysym| keysym != Keysym::NoSymbol) {
+            Some(keysym) => vec![keysym],
+            None => active_keysyms(state, keycode),
+        }
+    }
+}
+
+/// 當前佈局中的 keysym, 只有一個時應用大寫鎖定等轉換。
+fn active_keysyms(state: &xkb::State, keycode: Keycode) -> Vec<Keysym> {
+    match state.key_get_one_sym(keycode) {
+        Keysym::NoSymbol => state.key_get_syms(keycode).to_vec(),
+        keysym => vec![keysym],
+    }
+}
+
+/// 將狀態中的修飾鍵按名稱換算到另一 keymap.
+pub fn remap_mods(state: &xkb::State, keymap: &xkb::Keymap, component: StateComponent) -> ModMask {
+    let from = state.get_keymap();
+    let mask = state.serialize_mods(component);
+    (0..from.num_mods())
+        .filter(|&index| index < ModMask::BITS && mask & 1 << index != 0)
+        .map(|index| keymap.mod_get_index(from.mod_get_name(index)))
+        .filter(|&index| index != MOD_INVALID && index < ModMask::BITS)
+        .fold(0, |mask, index| mask | 1 << index)
+}
+
+/// 在新 keymap 上創建狀態，按名稱保留原有的修飾鍵和佈局組。
+pub fn carry_state(old: &xkb::State, keymap: &xkb::Keymap) -> xkb::State {
+    let mut state = xkb::State::new(keymap);
+    state.update_mask(
+        remap_mods(old, keymap, XKB_STATE_MODS_DEPRESSED),
+        remap_mods(old, keymap, XKB_STATE_MODS_LATCHED),
+        remap_mods(old, keymap, XKB_STATE_MODS_LOCKED),
+        old.serialize_layout(XKB_STATE_LAYOUT_DEPRESSED),
+        old.serialize_layout(XKB_STATE_LAYOUT_LATCHED),
+        old.serialize_layout(XKB_STATE_LAYOUT_LOCKED),
+    );
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use xkbcommon::xkb::KEYMAP_FORMAT_TEXT_V1;
+
+    use super::*;
+
+    const KEYMAP: &str = include_str!("im/tests/keymap.xkb");
+    /// evdev 鍵碼加 8
+    const N: u32 = 49 + 8;
+
+    /// `N` 鍵改爲給定符號的 keymap.
+    fn state(symbols: &str) -> xkb::State {
+        let text = KEYMAP.replace("key <AB06> { [ n, N ] };", symbols);
+        let context = xkb::Context::new(0);
+        let keymap = xkb::Keymap::new_from_string(
+            &context,
+            text,
+            KEYMAP_FORMAT_TEXT_V1,
+            KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .unwrap();
+        xkb::State::new(&keymap)
+    }
+
+    fn resolve(mapping: KeyMapping, state: &xkb::State) -> Vec<Keysym> {
+        let context = xkb::Context::new(0);
+        KeyResolver::new(&context, mapping).keysyms(state, Keycode::new(N))
+    }
+
+    #[test]
+    fn force_group() {
+        let mut state = state(
+            "key <AB06> { symbols[Group1] = [ n, N ], symbols[Group2] = [ Cyrillic_te, Cyrillic_TE ] };",
+        );
+        // 切換到第二組並按住 Shift
+        state.update_mask(1, 0, 0, 0, 0, 1);
+        assert_eq!(resolve(KeyMapping::AsTyped, &state), [Keysym::Cyrillic_TE]);
+        assert_eq!(resolve(KeyMapping::Group(1), &state), [Keysym::N]);
+        // 該鍵沒有第三組時使用當前佈局
+        assert_eq!(resolve(KeyMapping::Group(3), &state), [Keysym::Cyrillic_TE]);
+    }
+
+    #[test]
+    fn force_group_applies_caps_lock() {
+        // TWO_LEVEL 不消耗 Lock, 大寫由 keysym 轉換產生
+        let mut state = state(
+            "key <AB06> { type = \"TWO_LEVEL\", symbols[Group1] = [ n, N ], symbols[Group2] = [ Cyrillic_te, Cyrillic_TE ] };",
+        );
+        // 切換到第二組並鎖定 Lock
+        state.update_mask(0, 0, 2, 0, 0, 1);
+        assert_eq!(resolve(KeyMapping::AsTyped, &state), [Keysym::Cyrillic_TE]);
+        assert_eq!(resolve(KeyMapping::Group(1), &state), [Keysym::N]);
+    }
+
+    #[test]
+    fn force_group_follows_keymap() {
+        let context = xkb::Context::new(0);
+        let mut resolver = KeyResolver::new(&context, KeyMapping::Group(1));
+        let keycode = Keycode::new(N);
+        let group = |first: &str| {
+            let mut state = state(&format!(
+                "key <AB06> {{ symbols[Group1] = [ {first} ], symbols[Group2] = [ Cyrillic_te ] }};"
+            ));
+            state.update_mask(0, 0, 0, 0, 0, 1);
+            state
+        };
+        assert_eq!(resolver.keysyms(&group("n"), keycode), [Keysym::n]);
+        // 換 keymap 後重建鎖定狀態
+        assert_eq!(resolver.keysyms(&group("b"), keycode), [Keysym::b]);
+    }
+
+    #[test]
+    fn physical_us() {
+        // 測試 keymap 即 `us` 佈局，不依賴系統的 xkeyboard-config
+        let mut resolver = KeyResolver {
+            mapping: KeyMapping::PhysicalUs,
+            us: Some(state("key <AB06> { [ n, N ] };")),
+            forced: None,
+        };
+        let keycode = Keycode::new(N);
+        // Dvorak 中該鍵爲 b
+        let mut state = state("key <AB06> { [ b, B ] };");
+        assert_eq!(resolve(KeyMapping::AsTyped, &state), [Keysym::b]);
+        assert_eq!(resolver.keysyms(&state, keycode), [Keysym::n]);
+        state.update_mask(1, 0, 0, 0, 0, 0);
+        assert_eq!(resolver.keysyms(&state, keycode), [Keysym::N]);
+    }
+
+    #[test]
+    fn carry_locked_modifiers() {
+        let mut old = state("key <AB06> { [ n, N ] };");
+        old.update_mask(0, 0, 2, 0, 0, 0);
+        let new = carry_state(&old, &state("key <AB06> { [ b, B ] };").get_keymap());
+        assert_eq!(new.serialize_mods(XKB_STATE_MODS_LOCKED), 2);
+    }
+}
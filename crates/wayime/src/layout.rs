@@ -0,0 +1,199 @@
+/// 候選框尺寸參數，單位爲表面局部坐標。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutMetrics {
+    pub font_size: f64,
+    pub padding: f64,
+    pub line_spacing: f64,
+}
+
+impl Default for LayoutMetrics {
+    fn default() -> Self {
+        Self {
+            font_size: 16.0,
+            padding: 6.0,
+            line_spacing: 4.0,
+        }
+    }
+}
+
+impl LayoutMetrics {
+    pub fn line_height(&self) -> f64 {
+        self.font_size + self.line_spacing
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// 按縮放倍數換算到緩衝區坐標。
+    pub fn scale(self, factor: f64) -> Self {
+        Self {
+            x: self.x * factor,
+            y: self.y * factor,
+            width: self.width * factor,
+            height: self.height * factor,
+        }
+    }
+
+    /// 四邊向內收縮。
+    pub fn inset(self, by: f64) -> Self {
+        Self {
+            x: self.x + by,
+            y: self.y + by,
+            width: (self.width - 2.0 * by).max(0.0),
+            height: (self.height - 2.0 * by).max(0.0),
+        }
+    }
+
+    pub fn intersect(self, other: Self) -> Self {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        Self {
+            x,
+            y,
+            width: ((self.x + self.width).min(other.x + other.width) - x).max(0.0),
+            height: ((self.y + self.height).min(other.y + other.height) - y).max(0.0),
+        }
+    }
+}
+
+/// 候選框佈局：首行爲組合文本，候選縱向每行一個或橫向排成一行。
+///
+/// 繪製和指針命中測試共用同一佈局。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CandidateLayout {
+    pub width: f64,
+    pub height: f64,
+    pub preedit: Rect,
+    /// 當前頁候選的區域，下標即頁內序號
+    pub candidates: Vec<Rect>,
+}
+
+impl CandidateLayout {
+    /// `rows` 爲每個候選行的完整文本，`measure` 返回文本寬度。
+    pub fn vertical(
+        metrics: LayoutMetrics,
+        measure: impl Fn(&str) -> f64,
+        preedit: &str,
+        rows: &[String],
+    ) -> Self {
+        let line_height = metrics.line_height();
+        let mut width = measure(preedit);
+        let mut y = metrics.padding + line_height;
+        let mut candidates = Vec::new();
+        for row in rows {
+            width = width.max(measure(row));
+            candidates.push(Rect {
+                x: 0.0,
+                y,
+                width: 0.0,
+                height: line_height,
+            });
+            y += line_height;
+        }
+        let width = width + 2.0 * metrics.padding;
+        for rect in &mut candidates {
+            rect.width = width;
+        }
+        Self {
+            width,
+            height: y + metrics.padding,
+            preedit: Rect {
+                x: 0.0,
+                y: metrics.padding,
+                width,
+                height: line_height,
+            },
+            candidates,
+        }
+    }
+
+    /// 橫向排列，每個候選的區域寬爲文本寬度加內邊距。
+    pub fn horizontal(
+        metrics: LayoutMetrics,
+        measure: impl Fn(&str) -> f64,
+        preedit: &str,
+        rows: &[String],
+    ) -> Self {
+        let line_height = metrics.line_height();
+        let y = metrics.padding + line_height;
+        let mut x = 0.0;
+        let mut candidates = Vec::new();
+        for row in rows {
+            let width = measure(row) + 2.0 * metrics.padding;
+            candidates.push(Rect {
+                x,
+                y,
+                width,
+                height: line_height,
+            });
+            x += width;
+        }
+        let width = x.max(measure(preedit) + 2.0 * metrics.padding);
+        let height = if rows.is_empty() { y } else { y + line_height };
+        Self {
+            width,
+            height: height + metrics.padding,
+            preedit: Rect {
+                x: 0.0,
+                y: metrics.padding,
+                width,
+                height: line_height,
+            },
+            candidates,
+        }
+    }
+
+    /// 返回坐標處的候選頁內序號。
+    pub fn hit(&self, x: f64, y: f64) -> Option<usize> {
+        self.candidates.iter().position(|rect| rect.contains(x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每個字符寬半個字號。
+    fn measure(text: &str) -> f64 {
+        text.chars().count() as f64 * 8.0
+    }
+
+    #[test]
+    fn hit_rows() {
+        let rows = ["1. 你好".to_string(), "2. 擬好".to_string()];
+        let layout = CandidateLayout::vertical(LayoutMetrics::default(), measure, "nihao", &rows);
+        // 首行爲組合文本
+        assert_eq!(layout.hit(10.0, 10.0), None);
+        assert_eq!(layout.hit(10.0, 30.0), Some(0));
+        assert_eq!(layout.hit(10.0, 50.0), Some(1));
+        assert_eq!(layout.hit(10.0, 70.0), None);
+        // 寬度取最長行加內邊距
+        assert_eq!(layout.width, 5.0 * 8.0 + 2.0 * 6.0);
+        assert_eq!(layout.height, 6.0 + 3.0 * 20.0 + 6.0);
+        assert_eq!(layout.hit(52.0, 30.0), None);
+    }
+
+    #[test]
+    fn hit_columns() {
+        let rows = ["1. 你好".to_string(), "2. 擬好".to_string()];
+        let layout = CandidateLayout::horizontal(LayoutMetrics::default(), measure, "nihao", &rows);
+        // 每個候選寬 5 * 8 + 2 * 6
+        assert_eq!(layout.hit(10.0, 10.0), None);
+        assert_eq!(layout.hit(10.0, 30.0), Some(0));
+        assert_eq!(layout.hit(60.0, 30.0), Some(1));
+        assert_eq!(layout.hit(110.0, 30.0), None);
+        assert_eq!(layout.width, 104.0);
+        assert_eq!(layout.height, 6.0 + 2.0 * 20.0 + 6.0);
+    }
+}
@@ -1,18 +1,87 @@
+use std::{collections::HashMap, path::PathBuf, process::ExitCode};
+
+use binding::KeyBinding;
+use clap::{Parser, Subcommand};
+use codepoint::CodepointConfig;
+use emoji::EmojiConfig;
+use engine::Engine;
 use figment::{
     providers::{Format, Toml},
     Figment,
 };
 use im::Im;
+use keymap::KeyMapping;
+use preedit::{PreeditConfig, PreeditMode};
+use record::Recorder;
 use serde::{Deserialize, Deserializer};
+use switch::{InlineAsciiConfig, SwitchAction};
+use ui::{ExternalConfig, Renderer, ThemeConfig};
 use wayland_client::Connection;
 use xkbcommon::xkb::{Keysym, KEYSYM_NO_FLAGS};
 
+mod binding;
+mod clock;
+mod codepoint;
+mod compose;
+mod emoji;
 mod engine;
 mod im;
+mod keymap;
+mod layout;
+mod preedit;
+mod record;
+mod replay;
+mod shm;
+mod simulate;
+mod switch;
+mod test_schema;
+mod ui;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// 將收到的事件和輸入法的動作記錄到文件，用於報告問題
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// 記錄中保留提交和預編輯的文本，默認隱去
+    #[arg(long, requires = "record")]
+    record_commits: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-fn main() {
+#[derive(Subcommand)]
+enum Command {
+    /// 重放記錄文件，並打印與記錄輸出的差異
+    Replay {
+        /// `--record` 生成的記錄文件
+        file: PathBuf,
+        /// TOML 格式的模擬詞典，默認爲空
+        #[arg(long)]
+        dictionary: Option<PathBuf>,
+    },
+    /// 不經合成器模擬按鍵序列，打印每個按鍵後的狀態
+    Simulate {
+        /// 方案 id, 默認使用當前方案
+        #[arg(long)]
+        schema: Option<String>,
+        /// 以 JSON 行輸出
+        #[arg(long)]
+        json: bool,
+        /// 按鍵序列，如 `nihao{space}`, 省略時進入交互模式
+        keys: Option<String>,
+    },
+    /// 運行方案回歸測試用例，有用例失敗時返回非零
+    TestSchema {
+        /// TOML 格式的用例文件
+        cases: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
     // 初始化日誌輸出
     env_logger::init();
+    let cli = Cli::parse();
 
     // load config
     let config_file = dirs::config_dir()
@@ -25,6 +94,48 @@ fn main() {
         .expect("Fail to load config");
     dbg!(&config);
 
+    match cli.command {
+        Some(Command::Replay { file, dictionary }) => {
+            let dictionary: replay::Dictionary = match dictionary {
+                Some(path) => Figment::new()
+                    .merge(Toml::file_exact(path))
+                    .extract()
+                    .expect("fail to load dictionary"),
+                None => replay::Dictionary::default(),
+            };
+            let im = replay::im(config, dictionary).expect("fail to create input method");
+            match replay::replay(im, &file).expect("fail to replay") {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            }
+        }
+        Some(Command::Simulate { schema, json, keys }) => {
+            let mut engine = Engine::new().expect("fail to create engine");
+            match simulate::run(&mut engine, schema.as_deref(), keys.as_deref(), json) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("error: {err}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(Command::TestSchema { cases }) => {
+            let cases: test_schema::Cases = Figment::new()
+                .merge(Toml::file_exact(cases))
+                .extract()
+                .expect("fail to load test cases");
+            let mut engine = Engine::new().expect("fail to create engine");
+            match test_schema::run(&mut engine, &cases) {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            }
+        }
+        None => run(config, cli.record, cli.record_commits),
+    }
+}
+
+/// 作爲輸入法運行。
+fn run(config: Config, record: Option<PathBuf>, record_commits: bool) -> ExitCode {
     // 連接 wayland
     let conn = Connection::connect_to_env().unwrap();
     let mut event_queue = conn.new_event_queue();
@@ -32,6 +143,10 @@ fn main() {
 
     // 初始化輸入法
     let mut im = Im::new(config);
+    if let Some(path) = record {
+        let recorder = Recorder::create(&path, record_commits).expect("fail to create record file");
+        im.set_recorder(recorder);
+    }
     let display = conn.display();
     display.get_registry(&qh, ());
 
@@ -49,6 +164,83 @@ pub struct Config {
         default = "default_switch_key"
     )]
     pub switch_key: Keysym,
+    /// 按住切換鍵超過此毫秒數不算單擊
+    #[serde(default = "default_switch_timeout")]
+    pub switch_timeout: u32,
+    /// 組合中單擊切換鍵的動作
+    #[serde(default)]
+    pub switch_action: SwitchAction,
+    /// 臨時英文和大寫鎖定
+    #[serde(default)]
+    pub inline_ascii: InlineAsciiConfig,
+    /// 按方案 ID 覆蓋的配置
+    #[serde(default)]
+    pub schemas: HashMap<String, SchemaConfig>,
+    /// 從用戶詞典刪除高亮候選的快捷鍵
+    #[serde(default = "default_delete_candidate_keys")]
+    pub delete_candidate_keys: Vec<KeyBinding>,
+    /// 表情選擇
+    #[serde(default)]
+    pub emoji: EmojiConfig,
+    /// 碼位輸入
+    #[serde(default)]
+    pub codepoint: CodepointConfig,
+    /// 按鍵如何轉換爲送給 Rime 的 keysym
+    #[serde(default)]
+    pub key_mapping: KeyMapping,
+    /// 預編輯文本顯示輸入碼還是提交預覽
+    #[serde(default)]
+    pub preedit_mode: PreeditMode,
+    /// 預編輯文本格式
+    #[serde(default)]
+    pub preedit: PreeditConfig,
+    /// 候選界面後端
+    #[serde(default)]
+    pub renderer: Renderer,
+    /// 彈出窗口主題
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub external: ExternalConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            switch_key: default_switch_key(),
+            switch_timeout: default_switch_timeout(),
+            switch_action: SwitchAction::default(),
+            inline_ascii: InlineAsciiConfig::default(),
+            schemas: HashMap::new(),
+            delete_candidate_keys: default_delete_candidate_keys(),
+            emoji: EmojiConfig::default(),
+            codepoint: CodepointConfig::default(),
+            key_mapping: KeyMapping::default(),
+            preedit_mode: PreeditMode::default(),
+            preedit: PreeditConfig::default(),
+            renderer: Renderer::default(),
+            theme: ThemeConfig::default(),
+            external: ExternalConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// 方案的臨時英文配置，方案未配置時使用全局配置。
+    pub fn inline_ascii(&self, schema_id: Option<&str>) -> &InlineAsciiConfig {
+        schema_id
+            .and_then(|id| self.schemas.get(id))
+            .and_then(|schema| schema.inline_ascii.as_ref())
+            .unwrap_or(&self.inline_ascii)
+    }
+}
+
+/// 單個方案的配置，整節覆蓋全局配置。
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SchemaConfig {
+    #[serde(default)]
+    pub inline_ascii: Option<InlineAsciiConfig>,
 }
 
 fn deserialize_keysym_from_name<'de, D>(deserializer: D) -> Result<Keysym, D::Error>
@@ -62,3 +254,14 @@ where
 fn default_switch_key() -> Keysym {
     Keysym::XF86_Keyboard
 }
+
+fn default_switch_timeout() -> u32 {
+    500
+}
+
+fn default_delete_candidate_keys() -> Vec<KeyBinding> {
+    vec![
+        KeyBinding::new(binding::SHIFT, Keysym::Delete),
+        KeyBinding::new(binding::CONTROL, Keysym::Delete),
+    ]
+}
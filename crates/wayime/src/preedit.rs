@@ -0,0 +1,476 @@
+use rime_api::{CompositionSnapshot, ContextSnapshot};
+use serde::Deserialize;
+
+use crate::ui::UiModel;
+
+/// 高亮候選以此代替標籤。
+const HIGHLIGHT_MARKER: &str = "⁺";
+
+/// 預編輯文本格式。
+///
+/// ```toml
+/// [preedit]
+/// show-candidates = true
+/// label-style = "bracket"
+/// separator = " "
+/// show-comments = true
+/// max-candidates = 5
+/// caret = "‸"
+/// selection = true
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PreeditConfig {
+    /// 是否在預編輯文本中顯示候選
+    pub show_candidates: bool,
+    pub label_style: LabelStyle,
+    /// 候選之間的分隔符
+    pub separator: String,
+    /// 是否在候選後顯示註釋
+    pub show_comments: bool,
+    /// 最多顯示的候選數，默認顯示整頁
+    pub max_candidates: Option<usize>,
+    /// 插入到光標處的標記，默認不插入
+    pub caret: String,
+    /// 客戶端能否顯示選區，不能時有選區則隱藏光標
+    pub selection: bool,
+}
+
+impl Default for PreeditConfig {
+    fn default() -> Self {
+        Self {
+            show_candidates: true,
+            label_style: LabelStyle::default(),
+            separator: String::new(),
+            show_comments: false,
+            max_candidates: None,
+            caret: String::new(),
+            selection: true,
+        }
+    }
+}
+
+/// 預編輯文本中的組合部分。
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreeditMode {
+    /// 輸入碼，如 `ni hao`
+    #[default]
+    Code,
+    /// 將要提交的文本，如 `你好`
+    Preview,
+    /// 提交預覽後接輸入碼，如 `你好 ni hao`
+    Both,
+}
+
+impl PreeditMode {
+    /// 按模式選取組合文本，並映射光標和選區。
+    ///
+    /// 沒有提交預覽時使用輸入碼。
+    pub fn composition(self, context: &ContextSnapshot) -> CompositionSnapshot {
+        let code = &context.composition;
+        let (Some(preedit), Some(preview)) = (&code.preedit, &context.commit_text_preview) else {
+            return code.clone();
+        };
+        match self {
+            Self::Code => code.clone(),
+            Self::Preview => {
+                // 與輸入碼相同的前綴內位置不變，其後的位置映射到預覽末尾
+                let prefix = preedit
+                    .char_indices()
+                    .zip(preview.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(preedit.len().min(preview.len()), |((i, _), _)| i);
+                let map = |pos: i32| match usize::try_from(pos) {
+                    Ok(pos) if pos <= prefix => pos as i32,
+                    Ok(_) => preview.len() as i32,
+                    Err(_) => pos,
+                };
+                CompositionSnapshot {
+                    length: preview.len() as i32,
+                    cursor_pos: map(code.cursor_pos),
+                    sel_start: map(code.sel_start),
+                    sel_end: map(code.sel_end),
+                    preedit: Some(preview.clone()),
+                }
+            }
+            Self::Both => {
+                let offset = preview.len() as i32 + 1;
+                let shift = |pos: i32| if pos < 0 { pos } else { pos + offset };
+                CompositionSnapshot {
+                    length: code.length + offset,
+                    cursor_pos: shift(code.cursor_pos),
+                    sel_start: shift(code.sel_start),
+                    sel_end: shift(code.sel_end),
+                    preedit: Some(format!("{preview} {preedit}")),
+                }
+            }
+        }
+    }
+}
+
+/// 候選標籤樣式，標籤依次取 `select_labels`, `select_keys` 和數字。
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelStyle {
+    /// 上標，如 `¹`
+    #[default]
+    Superscript,
+    /// 方括號，如 `[1]`
+    Bracket,
+    /// 原樣顯示
+    Plain,
+}
+
+impl LabelStyle {
+    fn format(self, label: &str) -> String {
+        match self {
+            Self::Superscript => label.chars().map(superscript).collect(),
+            Self::Bracket => format!("[{label}]"),
+            Self::Plain => label.to_string(),
+        }
+    }
+}
+
+/// 發送給客戶端的預編輯文本，光標位置爲字節偏移。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    pub cursor_begin: i32,
+    pub cursor_end: i32,
+}
+
+impl Preedit {
+    /// 只含組合文本，用於在別處顯示候選的後端。
+    pub fn composition(model: &UiModel) -> Self {
+        let (cursor_begin, cursor_end) = cursor(&model.composition);
+        Self {
+            text: model.composition.preedit.clone().unwrap_or_default(),
+            cursor_begin,
+            cursor_end,
+        }
+    }
+
+    /// 有選區時改爲 -1/-1 隱藏光標，用於不能顯示選區的客戶端。
+    pub fn hide_selection(&mut self) {
+        if self.cursor_begin != self.cursor_end {
+            self.cursor_begin = -1;
+            self.cursor_end = -1;
+        }
+    }
+}
+
+impl PreeditConfig {
+    /// 按配置格式化預編輯文本，提示顯示在候選之後。
+    pub fn render(&self, model: &UiModel) -> Preedit {
+        let composition = &model.composition;
+        let Preedit {
+            mut text,
+            mut cursor_begin,
+            mut cursor_end,
+        } = Preedit::composition(model);
+
+        // 光標標記，選區起點在標記處時移到標記之後，終點則留在標記之前
+        if let Some(pos) = usize::try_from(composition.cursor_pos)
+            .ok()
+            .filter(|&pos| !self.caret.is_empty() && text.is_char_boundary(pos))
+        {
+            text.insert_str(pos, &self.caret);
+            let pos = pos as i32;
+            let caret = cursor_begin == cursor_end;
+            let shift = |cursor: i32, inclusive: bool| {
+                if cursor > pos || (inclusive && cursor == pos) {
+                    cursor + self.caret.len() as i32
+                } else {
+                    cursor
+                }
+            };
+            cursor_begin = shift(cursor_begin, true);
+            cursor_end = shift(cursor_end, caret);
+        }
+
+        if self.show_candidates {
+            let limit = self.max_candidates.unwrap_or(usize::MAX);
+            for (i, candidate) in model.candidates.iter().enumerate().take(limit) {
+                if i > 0 {
+                    text.push_str(&self.separator);
+                }
+                // 標籤或者高亮
+                if Some(i) == model.highlighted {
+                    text.push_str(HIGHLIGHT_MARKER);
+                } else {
+                    text.push_str(&self.label_style.format(&candidate.label));
+                }
+                text.push_str(&candidate.text);
+                if let Some(comment) = candidate.comment.as_deref().filter(|_| self.show_comments) {
+                    text.push(' ');
+                    text.push_str(comment);
+                }
+            }
+        }
+
+        // 刪除提示
+        if let Some(notice) = &model.notice {
+            text.push(' ');
+            text.push_str(notice);
+        }
+
+        // 多頁時顯示頁碼
+        if self.show_candidates && !model.candidates.is_empty() && !model.page.is_single() {
+            text.push(' ');
+            text.push_str(&model.page.position());
+        }
+
+        Preedit {
+            text,
+            cursor_begin,
+            cursor_end,
+        }
+    }
+}
+
+/// 組合文本中的光標，爲 UTF-8 字節偏移。
+///
+/// 有選區時高亮選區，否則光標置於 `cursor_pos`, 位置無效時隱藏光標。
+fn cursor(composition: &CompositionSnapshot) -> (i32, i32) {
+    let Some(preedit) = composition
+        .preedit
+        .as_deref()
+        .filter(|text| !text.is_empty())
+    else {
+        return (-1, -1);
+    };
+    let valid = |pos: i32| {
+        usize::try_from(pos)
+            .ok()
+            .filter(|&pos| preedit.is_char_boundary(pos))
+            .map(|_| pos)
+    };
+    match (
+        valid(composition.sel_start),
+        valid(composition.sel_end),
+        valid(composition.cursor_pos),
+    ) {
+        (Some(start), Some(end), _) if start < end => (start, end),
+        (_, _, Some(pos)) => (pos, pos),
+        _ => (-1, -1),
+    }
+}
+
+/// 上標數字，其餘字符不變。
+fn superscript(c: char) -> char {
+    match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rime_api::{CandidateSnapshot, MenuSnapshot, StatusSnapshot};
+
+    use super::*;
+
+    fn render(
+        config: &PreeditConfig,
+        mode: PreeditMode,
+        context: &ContextSnapshot,
+        notice: Option<&str>,
+    ) -> Preedit {
+        let model = UiModel::new(
+            context,
+            StatusSnapshot::default(),
+            mode,
+            notice.map(str::to_string),
+        );
+        config.render(&model)
+    }
+
+    fn context(select_keys: Option<&str>) -> ContextSnapshot {
+        let candidate = |text: &str, comment: Option<&str>| CandidateSnapshot {
+            text: text.to_string(),
+            comment: comment.map(str::to_string),
+        };
+        ContextSnapshot {
+            composition: CompositionSnapshot {
+                length: 5,
+                cursor_pos: 5,
+                sel_start: 0,
+                sel_end: 5,
+                preedit: Some("nihao".to_string()),
+            },
+            menu: MenuSnapshot {
+                page_size: 5,
+                page_no: 0,
+                is_last_page: true,
+                highlighted_candidate_index: 0,
+                candidates: vec![
+                    candidate("你好", Some("nǐ hǎo")),
+                    candidate("擬好", None),
+                    candidate("你号", None),
+                ],
+                select_keys: select_keys.map(str::to_string),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_format() {
+        let preedit = render(
+            &PreeditConfig::default(),
+            PreeditMode::Code,
+            &context(None),
+            None,
+        );
+        assert_eq!(preedit.text, "nihao⁺你好²擬好³你号");
+        assert_eq!((preedit.cursor_begin, preedit.cursor_end), (0, 5));
+    }
+
+    #[test]
+    fn labels_follow_select_keys() {
+        let config = PreeditConfig {
+            label_style: LabelStyle::Bracket,
+            separator: " ".to_string(),
+            show_comments: true,
+            max_candidates: Some(2),
+            ..Default::default()
+        };
+        let preedit = render(
+            &config,
+            PreeditMode::Code,
+            &context(Some("asd")),
+            Some("✗你号"),
+        );
+        assert_eq!(preedit.text, "nihao⁺你好 nǐ hǎo [s]擬好 ✗你号");
+    }
+
+    #[test]
+    fn hidden_candidates_and_caret() {
+        let config = PreeditConfig {
+            show_candidates: false,
+            caret: "‸".to_string(),
+            ..Default::default()
+        };
+        let preedit = render(&config, PreeditMode::Code, &context(None), None);
+        assert_eq!(preedit.text, "nihao‸");
+        // 選區終點留在標記之前
+        assert_eq!((preedit.cursor_begin, preedit.cursor_end), (0, 5));
+    }
+
+    fn composition(preedit: &str, sel: (i32, i32), cursor_pos: i32) -> CompositionSnapshot {
+        CompositionSnapshot {
+            length: preedit.len() as i32,
+            cursor_pos,
+            sel_start: sel.0,
+            sel_end: sel.1,
+            preedit: Some(preedit.to_string()),
+        }
+    }
+
+    #[test]
+    fn multibyte_cursor() {
+        // 「你」佔三個字節，選中其後的拼音
+        let context = ContextSnapshot {
+            composition: composition("你hao", (3, 6), 6),
+            ..context(None)
+        };
+        let preedit = render(&PreeditConfig::default(), PreeditMode::Code, &context, None);
+        assert_eq!(preedit.text, "你hao⁺你好²擬好³你号");
+        assert_eq!((preedit.cursor_begin, preedit.cursor_end), (3, 6));
+        assert_eq!(&preedit.text[3..6], "hao");
+
+        // 無選區時光標在 cursor_pos
+        assert_eq!(cursor(&composition("你好ma", (0, 0), 6)), (6, 6));
+        // 標記插入在選區起點時，選區移到標記之後
+        let config = PreeditConfig {
+            show_candidates: false,
+            caret: "‸".to_string(),
+            ..Default::default()
+        };
+        let context = ContextSnapshot {
+            composition: composition("你好ma", (6, 8), 6),
+            ..Default::default()
+        };
+        let preedit = render(&config, PreeditMode::Code, &context, None);
+        assert_eq!(preedit.text, "你好‸ma");
+        assert_eq!(
+            &preedit.text[preedit.cursor_begin as usize..preedit.cursor_end as usize],
+            "ma"
+        );
+    }
+
+    #[test]
+    fn selection_hidden() {
+        let context = ContextSnapshot {
+            composition: composition("你hao", (3, 6), 6),
+            ..Default::default()
+        };
+        let mut preedit = render(&PreeditConfig::default(), PreeditMode::Code, &context, None);
+        preedit.hide_selection();
+        assert_eq!((preedit.cursor_begin, preedit.cursor_end), (-1, -1));
+        // 沒有選區時保留光標
+        let context = ContextSnapshot {
+            composition: composition("你hao", (0, 0), 3),
+            ..Default::default()
+        };
+        let mut preedit = render(&PreeditConfig::default(), PreeditMode::Code, &context, None);
+        preedit.hide_selection();
+        assert_eq!((preedit.cursor_begin, preedit.cursor_end), (3, 3));
+    }
+
+    #[test]
+    fn invalid_cursor_hidden() {
+        // 落在多字節字符中間
+        assert_eq!(cursor(&composition("你好", (1, 2), 4)), (-1, -1));
+        assert_eq!(cursor(&composition("你好", (0, 9), 9)), (-1, -1));
+        assert_eq!(cursor(&CompositionSnapshot::default()), (-1, -1));
+        // 選區無效時退回光標
+        assert_eq!(cursor(&composition("你好", (0, 9), 3)), (3, 3));
+    }
+
+    #[test]
+    fn preview_modes() {
+        // 已確認「你」，其後爲未轉換的拼音
+        let context = ContextSnapshot {
+            composition: composition("你hao", (3, 6), 6),
+            commit_text_preview: Some("你好".to_string()),
+            ..Default::default()
+        };
+        let config = PreeditConfig {
+            caret: "‸".to_string(),
+            ..Default::default()
+        };
+
+        let preedit = render(&config, PreeditMode::Preview, &context, None);
+        assert_eq!(preedit.text, "你好‸");
+        assert_eq!(
+            &preedit.text[preedit.cursor_begin as usize..preedit.cursor_end as usize],
+            "好"
+        );
+
+        let preedit = render(&config, PreeditMode::Both, &context, None);
+        assert_eq!(preedit.text, "你好 你hao‸");
+        assert_eq!(
+            &preedit.text[preedit.cursor_begin as usize..preedit.cursor_end as usize],
+            "hao"
+        );
+
+        // 沒有預覽時使用輸入碼
+        let context = ContextSnapshot {
+            commit_text_preview: None,
+            ..context
+        };
+        let preedit = render(&config, PreeditMode::Preview, &context, None);
+        assert_eq!(preedit.text, "你hao‸");
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// 輸入法收到的事件。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Keymap {
+        format: u32,
+        hash: String,
+        text: String,
+    },
+    Key {
+        serial: u32,
+        time: u32,
+        key: u32,
+        state: u32,
+    },
+    Modifiers {
+        serial: u32,
+        depressed: u32,
+        latched: u32,
+        locked: u32,
+        group: u32,
+    },
+    RepeatInfo {
+        rate: i32,
+        delay: i32,
+    },
+    Activate,
+    Deactivate,
+    /// 不記錄周圍文本內容。
+    SurroundingText {
+        cursor: u32,
+        anchor: u32,
+    },
+    TextChangeCause {
+        cause: u32,
+    },
+    ContentType {
+        hint: u32,
+        purpose: u32,
+    },
+    Done,
+    Unavailable,
+}
+
+/// 輸入法發出的請求。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// 隱去時 `text` 的每個字節替換爲 `*`, 只保留長度和光標。
+    SetPreeditString {
+        text: String,
+        cursor_begin: i32,
+        cursor_end: i32,
+    },
+    /// `text` 爲 `None` 表示已隱去。
+    CommitString {
+        #[serde(default)]
+        text: Option<String>,
+    },
+    Commit {
+        serial: u32,
+    },
+    ForwardKeymap {
+        format: u32,
+        size: u32,
+    },
+    ForwardKey {
+        key: u32,
+        state: u32,
+    },
+    ForwardModifiers {
+        depressed: u32,
+        latched: u32,
+        locked: u32,
+        group: u32,
+    },
+}
+
+impl Action {
+    /// 隱去提交文本和預編輯文本。
+    pub fn redacted(self) -> Self {
+        match self {
+            Action::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => Action::SetPreeditString {
+                text: "*".repeat(text.len()),
+                cursor_begin,
+                cursor_end,
+            },
+            Action::CommitString { .. } => Action::CommitString { text: None },
+            action => action,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Record {
+    Event(Event),
+    Action(Action),
+}
+
+impl From<Event> for Record {
+    fn from(event: Event) -> Self {
+        Record::Event(event)
+    }
+}
+
+impl From<Action> for Record {
+    fn from(action: Action) -> Self {
+        Record::Action(action)
+    }
+}
+
+/// 記錄文件中的一行。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// 距記錄開始的毫秒數
+    pub t: u64,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+/// 按行寫入 JSON 記錄，每行立即落盤。
+pub struct Recorder {
+    writer: LineWriter<File>,
+    start: Instant,
+    /// 是否保留提交文本和預編輯文本
+    commits: bool,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, commits: bool) -> io::Result<Self> {
+        Ok(Self {
+            writer: LineWriter::new(File::create(path)?),
+            start: Instant::now(),
+            commits,
+        })
+    }
+
+    pub fn write(&mut self, record: Record) -> io::Result<()> {
+        let record = match record {
+            Record::Action(action) if !self.commits => Record::Action(action.redacted()),
+            record => record,
+        };
+        let entry = Entry {
+            t: self.start.elapsed().as_millis() as u64,
+            record,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+/// 讀取記錄文件。
+pub fn load(path: &Path) -> io::Result<Vec<Entry>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// keymap 摘要，用於比對不同記錄的 keymap.
+pub fn keymap_hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text))
+}
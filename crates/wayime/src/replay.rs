@@ -0,0 +1,119 @@
+use std::{collections::HashMap, io, path::Path};
+
+use rime_api::{MockDictionary, Rime};
+use serde::Deserialize;
+use similar::TextDiff;
+
+use crate::{
+    engine::Engine,
+    im::Im,
+    preedit::Preedit,
+    record::{self, Action, Record},
+    ui::{CandidateRenderer, InlineRenderer, NullRenderer, Renderer, UiModel},
+    Config,
+};
+
+pub use compositor::Harness;
+
+pub mod compositor;
+
+/// 重放使用的模擬詞典。
+///
+/// ```toml
+/// schema = "luna_pinyin"
+/// page-size = 5
+///
+/// [entries]
+/// nihao = ["你好", "擬好"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Dictionary {
+    /// 方案 id, 影響按方案的配置
+    pub schema: Option<String>,
+    pub page_size: Option<usize>,
+    /// 編碼到候選詞
+    pub entries: HashMap<String, Vec<String>>,
+}
+
+impl From<Dictionary> for MockDictionary {
+    fn from(dictionary: Dictionary) -> Self {
+        let mut mock = MockDictionary::new();
+        if let Some(schema) = &dictionary.schema {
+            mock = mock.schema(schema, schema);
+        }
+        if let Some(page_size) = dictionary.page_size {
+            mock = mock.page_size(page_size);
+        }
+        for (code, candidates) in &dictionary.entries {
+            mock = mock.entry(code, candidates.iter().map(String::as_str));
+        }
+        mock
+    }
+}
+
+/// 以模擬 Rime 新建用於重放的輸入法。
+///
+/// 彈出窗口和外部面板換成只設置組合文本的渲染器，
+/// 不創建表面或套接字，預編輯文本與原渲染器相同。
+pub fn im(config: Config, dictionary: Dictionary) -> io::Result<Im> {
+    let dictionary = MockDictionary::from(dictionary);
+    let engine = Engine::spawn(|| Ok(Rime::mock(dictionary))).map_err(io::Error::other)?;
+    let renderer: Box<dyn CandidateRenderer> = match config.renderer {
+        Renderer::Inline => Box::new(InlineRenderer::new(config.preedit.clone())),
+        Renderer::Popup | Renderer::External => Box::new(CompositionRenderer),
+        Renderer::None => Box::new(NullRenderer),
+    };
+    Ok(Im::with_renderer(config, engine, renderer))
+}
+
+/// 只設置組合文本。
+struct CompositionRenderer;
+
+impl CandidateRenderer for CompositionRenderer {
+    fn render(&mut self, model: &UiModel) -> Option<Preedit> {
+        Some(Preedit::composition(model))
+    }
+}
+
+/// 將記錄的事件重放給 `Im`, 打印與記錄動作的差異，返回兩者是否一致。
+pub fn replay(im: Im, path: &Path) -> io::Result<bool> {
+    let entries = record::load(path)?;
+    let mut harness = Harness::new(im)?;
+
+    let mut recorded = Vec::new();
+    for entry in entries {
+        match entry.record {
+            Record::Event(event) => harness.send(&event)?,
+            Record::Action(action) => recorded.push(action),
+        }
+    }
+
+    // 記錄已隱去文本時，重放結果同樣隱去
+    let redacted = recorded
+        .iter()
+        .all(|action| action.clone().redacted() == *action);
+    let replayed = harness
+        .take_actions()
+        .into_iter()
+        .map(|action| if redacted { action.redacted() } else { action })
+        .collect::<Vec<_>>();
+
+    let recorded = render(&recorded);
+    let replayed = render(&replayed);
+    if recorded == replayed {
+        println!("Replay matches recording");
+        return Ok(true);
+    }
+    let diff = TextDiff::from_lines(&recorded, &replayed);
+    print!("{}", diff.unified_diff().header("recorded", "replayed"));
+    Ok(false)
+}
+
+/// 每行一個動作。
+fn render(actions: &[Action]) -> String {
+    actions
+        .iter()
+        .map(|action| serde_json::to_string(action).unwrap() + "\n")
+        .collect()
+}
@@ -0,0 +1,524 @@
+use std::{
+    io::{self, ErrorKind, Write},
+    os::{fd::AsFd, unix::net::UnixStream},
+    sync::Arc,
+};
+
+use wayland_client::{backend::WaylandError, Connection, EventQueue};
+use wayland_protocols::wp::text_input::zv3::server::zwp_text_input_v3::{
+    ChangeCause, ContentHint, ContentPurpose,
+};
+use wayland_protocols_misc::{
+    zwp_input_method_v2::server::{
+        zwp_input_method_keyboard_grab_v2::{self, ZwpInputMethodKeyboardGrabV2},
+        zwp_input_method_manager_v2::{self, ZwpInputMethodManagerV2},
+        zwp_input_method_v2::{self, ZwpInputMethodV2},
+        zwp_input_popup_surface_v2::{self, ZwpInputPopupSurfaceV2},
+    },
+    zwp_virtual_keyboard_v1::server::{
+        zwp_virtual_keyboard_manager_v1::{self, ZwpVirtualKeyboardManagerV1},
+        zwp_virtual_keyboard_v1::{self, ZwpVirtualKeyboardV1},
+    },
+};
+use wayland_server::{
+    backend::{ClientData, ClientId, DisconnectReason},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_compositor::{self, WlCompositor},
+        wl_keyboard::{KeyState, KeymapFormat},
+        wl_pointer::{self, WlPointer},
+        wl_seat::{self, WlSeat},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::{self, WlShmPool},
+        wl_surface::{self, WlSurface},
+    },
+    Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::{
+    im::Im,
+    record::{Action, Event},
+    shm,
+};
+
+/// 觀察合成器收到的、不影響記錄動作的請求，默認忽略。
+pub trait Observer: 'static {
+    fn pointer(&mut self, _pointer: &WlPointer) {}
+    fn surface(&mut self, _surface: &WlSurface) {}
+    /// 附加到表面的緩衝區大小，卸下時爲 `None`
+    fn attach(&mut self, _size: Option<(i32, i32)>) {}
+    /// 轉發按鍵的時間戳
+    fn forward_key(&mut self, _time: u32) {}
+}
+
+impl Observer for () {}
+
+/// 假合成器狀態。
+pub struct Compositor<O = ()> {
+    input_method: Option<ZwpInputMethodV2>,
+    grab: Option<ZwpInputMethodKeyboardGrabV2>,
+    /// 按順序記錄的請求
+    pub actions: Vec<Action>,
+    pub observer: O,
+}
+
+/// 在同一線程內驅動 `Im` 和假合成器。
+pub struct Harness<O: Observer = ()> {
+    pub im: Im,
+    pub conn: Connection,
+    queue: EventQueue<Im>,
+    pub display: Display<Compositor<O>>,
+    pub compositor: Compositor<O>,
+}
+
+impl Harness {
+    /// 連接合成器並綁定全局對象。
+    pub fn new(im: Im) -> io::Result<Self> {
+        Self::with_observer(im, ())
+    }
+}
+
+impl<O: Observer> Harness<O> {
+    /// 同 [`Harness::new`], 請求另交給 `observer`.
+    pub fn with_observer(im: Im, observer: O) -> io::Result<Self> {
+        let (client_socket, server_socket) = UnixStream::pair()?;
+
+        // 服務端
+        let display = Display::<Compositor<O>>::new().map_err(io::Error::other)?;
+        let mut handle = display.handle();
+        handle.create_global::<Compositor<O>, WlCompositor, _>(6, ());
+        handle.create_global::<Compositor<O>, WlShm, _>(1, ());
+        handle.create_global::<Compositor<O>, WlSeat, _>(7, ());
+        handle.create_global::<Compositor<O>, ZwpInputMethodManagerV2, _>(1, ());
+        handle.create_global::<Compositor<O>, ZwpVirtualKeyboardManagerV1, _>(1, ());
+        handle.insert_client(server_socket, Arc::new(ClientState))?;
+
+        // 客戶端
+        let conn = Connection::from_socket(client_socket).map_err(io::Error::other)?;
+        let queue = conn.new_event_queue();
+        conn.display().get_registry(&queue.handle(), ());
+
+        let mut harness = Self {
+            im,
+            conn,
+            queue,
+            display,
+            compositor: Compositor {
+                input_method: None,
+                grab: None,
+                actions: Vec::new(),
+                observer,
+            },
+        };
+        harness.dispatch()?;
+        if harness.compositor.grab.is_none() {
+            return Err(io::Error::other("input method did not grab keyboard"));
+        }
+        Ok(harness)
+    }
+
+    /// 交替處理雙方消息，直至沒有新消息。
+    pub fn dispatch(&mut self) -> io::Result<()> {
+        loop {
+            self.conn.flush().map_err(io::Error::other)?;
+            let requests = self.display.dispatch_clients(&mut self.compositor)?;
+            self.display.flush_clients()?;
+            if let Some(guard) = self.queue.prepare_read() {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(err)) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => return Err(io::Error::other(err)),
+                }
+            }
+            let events = self
+                .queue
+                .dispatch_pending(&mut self.im)
+                .map_err(io::Error::other)?;
+            if requests == 0 && events == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// 向輸入法發送事件。
+    pub fn send(&mut self, event: &Event) -> io::Result<()> {
+        let (Some(input_method), Some(grab)) =
+            (&self.compositor.input_method, &self.compositor.grab)
+        else {
+            return Err(io::Error::other("input method is gone"));
+        };
+        match event {
+            Event::Keymap { format, text, .. } => {
+                let mut keymap = shm::anonymous_file()?;
+                keymap.write_all(text.as_bytes())?;
+                keymap.write_all(b"\0")?;
+                let format = KeymapFormat::try_from(*format).unwrap_or(KeymapFormat::XkbV1);
+                grab.keymap(format, keymap.as_fd(), text.len() as u32 + 1);
+            }
+            Event::Key {
+                serial,
+                time,
+                key,
+                state,
+            } => {
+                let state = KeyState::try_from(*state).unwrap_or(KeyState::Released);
+                grab.key(*serial, *time, *key, state);
+            }
+            Event::Modifiers {
+                serial,
+                depressed,
+                latched,
+                locked,
+                group,
+            } => grab.modifiers(*serial, *depressed, *latched, *locked, *group),
+            Event::RepeatInfo { rate, delay } => grab.repeat_info(*rate, *delay),
+            Event::Activate => input_method.activate(),
+            Event::Deactivate => input_method.deactivate(),
+            Event::SurroundingText { cursor, anchor } => {
+                input_method.surrounding_text(String::new(), *cursor, *anchor)
+            }
+            Event::TextChangeCause { cause } => {
+                let cause = (*cause).try_into().unwrap_or(ChangeCause::Other);
+                input_method.text_change_cause(cause)
+            }
+            Event::ContentType { hint, purpose } => {
+                let hint = ContentHint::from_bits_truncate(*hint);
+                let purpose = (*purpose).try_into().unwrap_or(ContentPurpose::Normal);
+                input_method.content_type(hint, purpose)
+            }
+            Event::Done => input_method.done(),
+            Event::Unavailable => input_method.unavailable(),
+        }
+        self.dispatch()
+    }
+
+    /// 取出已記錄的請求。
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.compositor.actions)
+    }
+}
+
+struct ClientState;
+
+impl ClientData for ClientState {
+    fn initialized(&self, _: ClientId) {}
+
+    fn disconnected(&self, _: ClientId, _: DisconnectReason) {}
+}
+
+impl<O: Observer> GlobalDispatch<WlSeat, ()> for Compositor<O> {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlSeat>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let seat = data_init.init(resource, ());
+        seat.capabilities(wl_seat::Capability::Keyboard | wl_seat::Capability::Pointer);
+    }
+}
+
+impl<O: Observer> Dispatch<WlSeat, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WlSeat,
+        request: wl_seat::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_seat::Request::GetPointer { id } = request {
+            let pointer = data_init.init(id, ());
+            state.observer.pointer(&pointer);
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<WlPointer, ()> for Compositor<O> {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlPointer,
+        _: wl_pointer::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl<O: Observer> GlobalDispatch<WlCompositor, ()> for Compositor<O> {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlCompositor>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<O: Observer> Dispatch<WlCompositor, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_compositor::Request::CreateSurface { id } = request {
+            let surface = data_init.init(id, ());
+            state.observer.surface(&surface);
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<WlSurface, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WlSurface,
+        request: wl_surface::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_surface::Request::Attach { buffer, .. } = request {
+            let size = buffer.and_then(|buffer| buffer.data::<(i32, i32)>().copied());
+            state.observer.attach(size);
+        }
+    }
+}
+
+impl<O: Observer> GlobalDispatch<WlShm, ()> for Compositor<O> {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlShm>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<O: Observer> Dispatch<WlShm, ()> for Compositor<O> {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlShm,
+        request: wl_shm::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm::Request::CreatePool { id, .. } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<WlShmPool, ()> for Compositor<O> {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlShmPool,
+        request: wl_shm_pool::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        // 緩衝區以其大小爲數據
+        if let wl_shm_pool::Request::CreateBuffer {
+            id, width, height, ..
+        } = request
+        {
+            data_init.init(id, (width, height));
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<WlBuffer, (i32, i32)> for Compositor<O> {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlBuffer,
+        _: wl_buffer::Request,
+        _: &(i32, i32),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl<O: Observer> Dispatch<ZwpInputPopupSurfaceV2, ()> for Compositor<O> {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &ZwpInputPopupSurfaceV2,
+        _: zwp_input_popup_surface_v2::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl<O: Observer> GlobalDispatch<ZwpInputMethodManagerV2, ()> for Compositor<O> {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<ZwpInputMethodManagerV2>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<O: Observer> Dispatch<ZwpInputMethodManagerV2, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpInputMethodManagerV2,
+        request: zwp_input_method_manager_v2::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_input_method_manager_v2::Request::GetInputMethod { input_method, .. } = request {
+            state.input_method = Some(data_init.init(input_method, ()));
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<ZwpInputMethodV2, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpInputMethodV2,
+        request: zwp_input_method_v2::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_input_method_v2::Request::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => state.actions.push(Action::SetPreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            }),
+            zwp_input_method_v2::Request::CommitString { text } => state
+                .actions
+                .push(Action::CommitString { text: Some(text) }),
+            zwp_input_method_v2::Request::Commit { serial } => {
+                state.actions.push(Action::Commit { serial })
+            }
+            zwp_input_method_v2::Request::GrabKeyboard { keyboard } => {
+                state.grab = Some(data_init.init(keyboard, ()));
+            }
+            zwp_input_method_v2::Request::GetInputPopupSurface { id, .. } => {
+                data_init.init(id, ());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<ZwpInputMethodKeyboardGrabV2, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpInputMethodKeyboardGrabV2,
+        request: zwp_input_method_keyboard_grab_v2::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_input_method_keyboard_grab_v2::Request::Release = request {
+            state.grab = None;
+        }
+    }
+}
+
+impl<O: Observer> GlobalDispatch<ZwpVirtualKeyboardManagerV1, ()> for Compositor<O> {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<ZwpVirtualKeyboardManagerV1>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<O: Observer> Dispatch<ZwpVirtualKeyboardManagerV1, ()> for Compositor<O> {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &ZwpVirtualKeyboardManagerV1,
+        request: zwp_virtual_keyboard_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_virtual_keyboard_manager_v1::Request::CreateVirtualKeyboard { id, .. } = request
+        {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl<O: Observer> Dispatch<ZwpVirtualKeyboardV1, ()> for Compositor<O> {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpVirtualKeyboardV1,
+        request: zwp_virtual_keyboard_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_virtual_keyboard_v1::Request::Keymap { format, size, .. } => {
+                state.actions.push(Action::ForwardKeymap { format, size })
+            }
+            zwp_virtual_keyboard_v1::Request::Key {
+                time,
+                key,
+                state: s,
+                ..
+            } => {
+                state.observer.forward_key(time);
+                state.actions.push(Action::ForwardKey { key, state: s })
+            }
+            zwp_virtual_keyboard_v1::Request::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+            } => state.actions.push(Action::ForwardModifiers {
+                depressed: mods_depressed,
+                latched: mods_latched,
+                locked: mods_locked,
+                group,
+            }),
+            _ => {}
+        }
+    }
+}
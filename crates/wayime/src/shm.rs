@@ -0,0 +1,60 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// 創建無路徑的讀寫文件，用於 `wl_shm` 池和 keymap 的文件描述符。
+///
+/// 放在 `XDG_RUNTIME_DIR` 中，未設置時使用系統臨時目錄。
+pub fn anonymous_file() -> io::Result<File> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    loop {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("wayime-{}-{n}", process::id()));
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(file) => {
+                fs::remove_file(&path)?;
+                return Ok(file);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Seek, Write},
+        os::fd::AsRawFd,
+    };
+
+    use super::*;
+
+    #[test]
+    fn file_is_unlinked_and_writable() {
+        let mut file = anonymous_file().unwrap();
+        let link = fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+        assert!(link.to_string_lossy().ends_with("(deleted)"));
+
+        file.write_all(b"keymap").unwrap();
+        file.rewind().unwrap();
+        let mut text = String::new();
+        file.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "keymap");
+    }
+}
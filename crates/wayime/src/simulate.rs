@@ -0,0 +1,298 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+};
+
+use rime_api::{CompositionSnapshot, MenuSnapshot, StatusSnapshot};
+use serde::Serialize;
+
+use crate::engine::{Engine, PageInfo};
+
+/// 每個按鍵之後的完整狀態。
+#[derive(Clone, Debug, Serialize)]
+pub struct Step {
+    pub key: String,
+    pub input: Option<String>,
+    pub composition: CompositionSnapshot,
+    pub menu: MenuSnapshot,
+    pub page: PageInfo,
+    pub commit: Option<String>,
+    pub status: StatusSnapshot,
+}
+
+/// 將按鍵序列拆分爲單個按鍵，`{space}` 等鍵名作爲一個按鍵。
+pub fn split_keys(key_sequence: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let mut rest = key_sequence;
+    while let Some(c) = rest.chars().next() {
+        let len = match rest.find('}') {
+            Some(end) if c == '{' => end + 1,
+            _ => c.len_utf8(),
+        };
+        let (key, tail) = rest.split_at(len);
+        keys.push(key);
+        rest = tail;
+    }
+    keys
+}
+
+/// 逐鍵模擬按鍵序列，返回每個按鍵後的狀態。
+pub fn simulate(engine: &mut Engine, key_sequence: &str) -> rime_api::Result<Vec<Step>> {
+    split_keys(key_sequence)
+        .into_iter()
+        .map(|key| {
+            engine.simulate(key)?;
+            Ok(step(engine, key))
+        })
+        .collect()
+}
+
+fn step(engine: &mut Engine, key: &str) -> Step {
+    let context = engine.context();
+    Step {
+        key: key.to_string(),
+        input: engine.input(),
+        composition: context.composition,
+        menu: context.menu,
+        page: engine.page_info(),
+        commit: engine.get_commit(),
+        status: engine.status(),
+    }
+}
+
+/// 運行模擬器，未給出按鍵序列時進入交互模式。
+pub fn run(
+    engine: &mut Engine,
+    schema: Option<&str>,
+    key_sequence: Option<&str>,
+    json: bool,
+) -> rime_api::Result<()> {
+    if let Some(schema) = schema {
+        engine.select_schema(schema)?;
+    }
+    match key_sequence {
+        Some(key_sequence) => {
+            for step in simulate(engine, key_sequence)? {
+                print_step(&step, json);
+            }
+            Ok(())
+        }
+        None => {
+            repl(engine, json);
+            Ok(())
+        }
+    }
+}
+
+/// 交互模式，每行一個按鍵序列。
+fn repl(engine: &mut Engine, json: bool) {
+    eprintln!("Enter key sequences like `nihao{{space}}`, or commands:");
+    eprintln!(
+        "  :schema <id>  :select <n>  :highlight <n>  :delete <n>  :page up|down  :reset  :quit"
+    );
+    let stdin = io::stdin();
+    loop {
+        eprint!("> ");
+        let _ = io::stderr().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let result = match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => continue,
+            (":quit", _) => break,
+            (":schema", schema_id) => engine.select_schema(schema_id.trim()),
+            (":reset", _) => {
+                engine.reset();
+                Ok(())
+            }
+            (":page", direction) => {
+                let backward = direction.trim() == "up";
+                navigate(engine, json, ":page", |engine| engine.change_page(backward))
+            }
+            (command @ (":select" | ":highlight" | ":delete"), index) => {
+                // 序號從 1 開始
+                let Some(index) = index.trim().parse::<usize>().ok().filter(|&i| i > 0) else {
+                    eprintln!("error: expect a candidate number");
+                    continue;
+                };
+                navigate(engine, json, command, |engine| match command {
+                    ":select" => engine.select(index - 1),
+                    ":highlight" => engine.highlight(index - 1),
+                    _ => engine.delete(index - 1),
+                })
+            }
+            _ => simulate(engine, line).map(|steps| {
+                for step in steps {
+                    print_step(&step, json);
+                }
+            }),
+        };
+        if let Err(err) = result {
+            eprintln!("error: {err}");
+        }
+    }
+}
+
+/// 運行候選操作並打印狀態。
+fn navigate(
+    engine: &mut Engine,
+    json: bool,
+    key: &str,
+    f: impl FnOnce(&mut Engine) -> bool,
+) -> rime_api::Result<()> {
+    if !f(engine) {
+        eprintln!("{key}: not applied");
+    }
+    print_step(&step(engine, key), json);
+    Ok(())
+}
+
+fn print_step(step: &Step, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string(step).unwrap());
+    } else {
+        print!("{}", format_step(step));
+    }
+}
+
+/// 文本格式的狀態。
+pub fn format_step(step: &Step) -> String {
+    let mut buf = String::new();
+    let _ = writeln!(buf, "{}", step.key);
+    let input = step.input.as_deref().unwrap_or("");
+    let _ = writeln!(buf, "  input:       {input}");
+    let _ = writeln!(
+        buf,
+        "  composition: {}",
+        format_composition(&step.composition)
+    );
+    let _ = writeln!(buf, "  menu:        {}", format_page(step));
+    for (i, candidate) in step.menu.candidates.iter().enumerate() {
+        let highlight = if i as i32 == step.menu.highlighted_candidate_index {
+            '>'
+        } else {
+            ' '
+        };
+        let label = step.page.label(i);
+        let comment = candidate.comment.as_deref().unwrap_or("");
+        let _ = writeln!(buf, "    {highlight}{label}. {} {comment}", candidate.text);
+    }
+    let commit = step.commit.as_deref().unwrap_or("");
+    let _ = writeln!(buf, "  commit:      {commit}");
+    let _ = writeln!(buf, "  status:      {}", format_status(&step.status));
+    buf
+}
+
+/// 以 `[` `]` 標出選區，`‸` 標出光標。
+fn format_composition(composition: &CompositionSnapshot) -> String {
+    let Some(preedit) = &composition.preedit else {
+        return String::new();
+    };
+    let position = |pos: i32| {
+        usize::try_from(pos)
+            .ok()
+            .filter(|&pos| preedit.is_char_boundary(pos))
+    };
+    let marks = [
+        (position(composition.sel_start), '['),
+        (position(composition.sel_end), ']'),
+        (position(composition.cursor_pos), '‸'),
+    ];
+    let mut buf = String::new();
+    for (i, c) in preedit.char_indices().chain([(preedit.len(), '\0')]) {
+        for (pos, mark) in marks {
+            if pos == Some(i) {
+                buf.push(mark);
+            }
+        }
+        if c != '\0' {
+            buf.push(c);
+        }
+    }
+    buf
+}
+
+fn format_page(step: &Step) -> String {
+    if step.menu.candidates.is_empty() {
+        return String::new();
+    }
+    format!(
+        "page {} (size {})",
+        step.page.position(),
+        step.page.page_size
+    )
+}
+
+fn format_status(status: &StatusSnapshot) -> String {
+    let mut buf = String::new();
+    if let Some(schema_id) = &status.schema_id {
+        buf.push_str(schema_id);
+    }
+    if let Some(schema_name) = &status.schema_name {
+        let _ = write!(buf, " ({schema_name})");
+    }
+    let flags = [
+        (status.is_disabled, "disabled"),
+        (status.is_composing, "composing"),
+        (status.is_ascii_mode, "ascii_mode"),
+        (status.is_full_shape, "full_shape"),
+        (status.is_simplified, "simplified"),
+        (status.is_traditional, "traditional"),
+        (status.is_ascii_punct, "ascii_punct"),
+    ];
+    for (_, flag) in flags.iter().filter(|(set, _)| *set) {
+        let _ = write!(buf, " {flag}");
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use rime_api::{MockDictionary, Rime};
+
+    use super::*;
+
+    #[test]
+    fn split_key_names() {
+        assert_eq!(
+            split_keys("ni{space}好{Shift+Return}{"),
+            ["n", "i", "{space}", "好", "{Shift+Return}", "{"]
+        );
+    }
+
+    #[test]
+    fn steps_until_commit() {
+        let dictionary = MockDictionary::new()
+            .schema("mock", "Mock")
+            .entry("nihao", [("你好", "nǐ hǎo"), ("擬好", "")]);
+        let mut engine = Engine::spawn(|| Ok(Rime::mock(dictionary))).unwrap();
+        let steps = simulate(&mut engine, "nihao{space}").unwrap();
+        assert_eq!(steps.len(), 6);
+
+        let composing = &steps[4];
+        assert_eq!(composing.input.as_deref(), Some("nihao"));
+        assert_eq!(composing.menu.candidates.len(), 2);
+        assert_eq!(composing.commit, None);
+        assert!(format_step(composing).contains("    >1. 你好 nǐ hǎo\n"));
+
+        let committed = &steps[5];
+        assert_eq!(committed.key, "{space}");
+        assert_eq!(committed.commit.as_deref(), Some("你好"));
+        assert!(!committed.status.is_composing);
+    }
+
+    #[test]
+    fn composition_marks() {
+        let composition = CompositionSnapshot {
+            length: 6,
+            cursor_pos: 6,
+            sel_start: 0,
+            sel_end: 3,
+            preedit: Some("你hao".to_string()),
+        };
+        assert_eq!(format_composition(&composition), "[你]hao‸");
+    }
+}
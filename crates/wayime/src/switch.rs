@@ -0,0 +1,137 @@
+use serde::Deserialize;
+use xkbcommon::xkb::Keysym;
+
+/// 組合中單擊切換鍵時的動作，同 Rime `ascii_composer/switch_key`.
+///
+/// 未在組合時單擊總是切換 ASCII 模式。
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SwitchAction {
+    /// 上屏輸入碼並切換
+    #[default]
+    CommitCode,
+    /// 清除輸入碼並切換
+    Clear,
+    /// 保留輸入碼，臨時輸入英文，空格或回車上屏
+    InlineAscii,
+    /// 組合中不切換
+    Noop,
+}
+
+/// 臨時英文的觸發方式，可按方案覆蓋。
+///
+/// ```toml
+/// [inline-ascii]
+/// prefixes = ["v"]
+///
+/// [schemas.luna_pinyin.inline-ascii]
+/// shift-letter = false
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct InlineAsciiConfig {
+    /// 組合中 Shift 加字母進入臨時英文
+    pub shift_letter: bool,
+    /// 輸入碼恰爲其中之一時清除並進入臨時英文
+    pub prefixes: Vec<String>,
+    /// 大寫鎖定時的處理
+    pub caps_lock: CapsLock,
+}
+
+impl Default for InlineAsciiConfig {
+    fn default() -> Self {
+        Self {
+            shift_letter: true,
+            prefixes: Vec::new(),
+            caps_lock: CapsLock::default(),
+        }
+    }
+}
+
+/// 大寫鎖定時按鍵的去向。
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CapsLock {
+    /// 上屏輸入碼，按鍵直接轉發
+    #[default]
+    Bypass,
+    /// 照常交給 Rime
+    Rime,
+}
+
+/// 切換鍵單擊檢測。
+///
+/// 按下時沒有按住其他修飾鍵、其間沒有其他按鍵且在限時內鬆開纔算單擊。
+#[derive(Debug)]
+pub struct TapDetector {
+    key: Keysym,
+    /// 最長按住時間，毫秒
+    timeout: u32,
+    /// 切換鍵按下時的時間戳
+    pressed_at: Option<u32>,
+}
+
+impl TapDetector {
+    pub fn new(key: Keysym, timeout: u32) -> Self {
+        Self {
+            key,
+            timeout,
+            pressed_at: None,
+        }
+    }
+
+    /// 處理按鍵事件，返回是否完成一次單擊。
+    ///
+    /// `held` 爲按下前是否已按住其他修飾鍵，`time` 爲事件時間戳。
+    pub fn key(&mut self, keysym: Keysym, pressed: bool, held: bool, time: u32) -> bool {
+        if keysym != self.key {
+            self.cancel();
+            return false;
+        }
+        if pressed {
+            self.pressed_at = (!held).then_some(time);
+            return false;
+        }
+        self.pressed_at
+            .take()
+            .is_some_and(|at| time.wrapping_sub(at) <= self.timeout)
+    }
+
+    /// 其他輸入打斷單擊，如點擊指針。
+    pub fn cancel(&mut self) {
+        self.pressed_at = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tap() {
+        let mut tap = TapDetector::new(Keysym::Shift_L, 300);
+        assert!(!tap.key(Keysym::Shift_L, true, false, 100));
+        assert!(tap.key(Keysym::Shift_L, false, false, 200));
+        // 鬆開只算一次
+        assert!(!tap.key(Keysym::Shift_L, false, false, 210));
+
+        // 按住過久
+        tap.key(Keysym::Shift_L, true, false, 1000);
+        assert!(!tap.key(Keysym::Shift_L, false, false, 1301));
+
+        // 其間有其他按鍵
+        tap.key(Keysym::Shift_L, true, false, 2000);
+        tap.key(Keysym::a, true, false, 2010);
+        tap.key(Keysym::a, false, false, 2020);
+        assert!(!tap.key(Keysym::Shift_L, false, false, 2030));
+
+        // 按住其他修飾鍵
+        tap.key(Keysym::Shift_L, true, true, 3000);
+        assert!(!tap.key(Keysym::Shift_L, false, false, 3010));
+
+        // 指針打斷
+        tap.key(Keysym::Shift_L, true, false, 4000);
+        tap.cancel();
+        assert!(!tap.key(Keysym::Shift_L, false, false, 4010));
+    }
+}
@@ -0,0 +1,188 @@
+use serde::Deserialize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::{engine::Engine, simulate::simulate};
+
+/// 測試用例文件。
+///
+/// ```toml
+/// schema = "luna_pinyin"
+///
+/// [[case]]
+/// keys = "nihao{space}"
+/// commit = "你好"
+///
+/// [[case]]
+/// name = "top candidates"
+/// keys = "nihao"
+/// candidates = ["你好", "擬好"]
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Cases {
+    /// 用例未指定方案時使用
+    #[serde(default)]
+    pub schema: Option<String>,
+    #[serde(default, rename = "case")]
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Case {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub schema: Option<String>,
+    /// 按鍵序列，如 `nihao{space}`
+    pub keys: String,
+    /// 期望的提交文本，多次提交依次連接
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// 期望的前幾個候選詞
+    #[serde(default)]
+    pub candidates: Option<Vec<String>>,
+}
+
+impl Case {
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.keys)
+    }
+}
+
+/// 運行所有用例並打印結果，返回是否全部通過。
+pub fn run(engine: &mut Engine, cases: &Cases) -> bool {
+    let mut failed = 0;
+    for case in &cases.cases {
+        match check(engine, cases.schema.as_deref(), case) {
+            None => println!("ok   {}", case.name()),
+            Some(report) => {
+                failed += 1;
+                println!("FAIL {}", case.name());
+                print!("{report}");
+            }
+        }
+    }
+    let passed = cases.cases.len() - failed;
+    println!("{passed} passed, {failed} failed");
+    failed == 0
+}
+
+/// 運行單個用例，失敗時返回差異報告。
+pub fn check(engine: &mut Engine, default_schema: Option<&str>, case: &Case) -> Option<String> {
+    // 清除上一個用例的狀態
+    engine.reset();
+    engine.get_commit();
+
+    if let Some(schema) = case.schema.as_deref().or(default_schema) {
+        if let Err(err) = engine.select_schema(schema) {
+            return Some(format!("  cannot select schema `{schema}`: {err}\n"));
+        }
+    }
+    let steps = match simulate(engine, &case.keys) {
+        Ok(steps) => steps,
+        Err(err) => return Some(format!("  cannot simulate `{}`: {err}\n", case.keys)),
+    };
+
+    let mut report = String::new();
+    if let Some(expected) = &case.commit {
+        let actual = steps
+            .iter()
+            .filter_map(|step| step.commit.as_deref())
+            .collect::<String>();
+        if *expected != actual {
+            report += &diff("commit", &[expected.as_str()], &[actual.as_str()]);
+        }
+    }
+    if let Some(expected) = &case.candidates {
+        let actual = steps
+            .last()
+            .map(|step| &step.menu.candidates[..])
+            .unwrap_or_default()
+            .iter()
+            .take(expected.len())
+            .map(|candidate| candidate.text.as_str())
+            .collect::<Vec<_>>();
+        let expected = expected.iter().map(String::as_str).collect::<Vec<_>>();
+        if expected != actual {
+            report += &diff("candidates", &expected, &actual);
+        }
+    }
+    (!report.is_empty()).then_some(report)
+}
+
+/// 逐行差異，`-` 爲期望，`+` 爲實際。
+fn diff(label: &str, expected: &[&str], actual: &[&str]) -> String {
+    let expected = expected.join("\n") + "\n";
+    let actual = actual.join("\n") + "\n";
+    let mut buf = format!("  {label}:\n");
+    for change in TextDiff::from_lines(&expected, &actual).iter_all_changes() {
+        let sign = match change.tag() {
+            ChangeTag::Delete => '-',
+            ChangeTag::Insert => '+',
+            ChangeTag::Equal => ' ',
+        };
+        buf += &format!("    {sign} {change}");
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        providers::{Format, Toml},
+        Figment,
+    };
+    use rime_api::{MockDictionary, Rime};
+
+    use super::*;
+
+    fn engine() -> Engine {
+        let dictionary = MockDictionary::new()
+            .schema("mock", "Mock")
+            .entry("nihao", ["你好", "擬好"])
+            .entry("ma", ["嗎", "媽"]);
+        Engine::spawn(|| Ok(Rime::mock(dictionary))).unwrap()
+    }
+
+    fn cases(toml: &str) -> Cases {
+        Figment::new().merge(Toml::string(toml)).extract().unwrap()
+    }
+
+    #[test]
+    fn passing_cases() {
+        let cases = cases(
+            r#"
+            schema = "mock"
+
+            [[case]]
+            keys = "nihao{space}ma2"
+            commit = "你好媽"
+
+            [[case]]
+            keys = "nihao"
+            candidates = ["你好"]
+            "#,
+        );
+        let mut engine = engine();
+        assert!(cases
+            .cases
+            .iter()
+            .all(|case| check(&mut engine, cases.schema.as_deref(), case).is_none()));
+    }
+
+    #[test]
+    fn failing_case_reports_diff() {
+        let cases = cases(
+            r#"
+            [[case]]
+            name = "wrong order"
+            keys = "nihao"
+            candidates = ["擬好", "你好"]
+            "#,
+        );
+        let report = check(&mut engine(), None, &cases.cases[0]).unwrap();
+        assert_eq!(
+            report,
+            "  candidates:\n    + 你好\n      擬好\n    - 你好\n"
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use rime_api::{CompositionSnapshot, ContextSnapshot, StatusSnapshot};
+use serde::{Deserialize, Serialize};
+use wayland_client::{
+    protocol::{wl_compositor::WlCompositor, wl_shm::WlShm, wl_surface::WlSurface},
+    QueueHandle,
+};
+use wayland_protocols::wp::{
+    fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+    viewporter::client::wp_viewporter::WpViewporter,
+};
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_v2::ZwpInputMethodV2;
+
+use crate::{
+    engine::PageInfo,
+    im::Im,
+    layout::CandidateLayout,
+    preedit::{Preedit, PreeditMode},
+    Config,
+};
+
+pub use external::{ExternalConfig, ExternalRenderer};
+pub use inline::InlineRenderer;
+pub use null::NullRenderer;
+pub use popup::PopupRenderer;
+pub use theme::{Color, Colors, Orientation, ThemeConfig};
+
+mod external;
+mod inline;
+mod null;
+mod popup;
+mod theme;
+
+/// 界面模型，每次更新時由引擎狀態生成。
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct UiModel {
+    /// 按 `preedit-mode` 選取的組合文本，光標和選區爲字節偏移
+    pub composition: CompositionSnapshot,
+    pub candidates: Vec<UiCandidate>,
+    /// 高亮候選的頁內序號
+    pub highlighted: Option<usize>,
+    pub page: PageInfo,
+    pub status: StatusSnapshot,
+    /// 如刪除候選的提示
+    pub notice: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct UiCandidate {
+    /// 未加樣式的標籤，依次取 `select_labels`, `select_keys` 和數字
+    pub label: String,
+    pub text: String,
+    pub comment: Option<String>,
+}
+
+impl UiModel {
+    pub fn new(
+        context: &ContextSnapshot,
+        status: StatusSnapshot,
+        mode: PreeditMode,
+        notice: Option<String>,
+    ) -> Self {
+        let menu = &context.menu;
+        let page = PageInfo::from_context(context);
+        let candidates = menu
+            .candidates
+            .iter()
+            .enumerate()
+            .map(|(i, candidate)| UiCandidate {
+                label: page.label(i),
+                text: candidate.text.clone(),
+                comment: candidate.comment.clone(),
+            })
+            .collect::<Vec<_>>();
+        let highlighted = usize::try_from(menu.highlighted_candidate_index)
+            .ok()
+            .filter(|&i| i < candidates.len());
+        Self {
+            composition: mode.composition(context),
+            candidates,
+            highlighted,
+            page,
+            status,
+            notice,
+        }
+    }
+
+    /// 在光標處插入進行中的 Compose 序列並選中。
+    pub fn insert_pending(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let composition = &mut self.composition;
+        let preedit = composition.preedit.get_or_insert_with(String::new);
+        let pos = usize::try_from(composition.cursor_pos)
+            .ok()
+            .filter(|&pos| preedit.is_char_boundary(pos))
+            .unwrap_or(preedit.len());
+        preedit.insert_str(pos, text);
+        let (pos, len) = (pos as i32, text.len() as i32);
+        composition.length += len;
+        composition.sel_start = pos;
+        composition.sel_end = pos + len;
+        composition.cursor_pos = pos + len;
+    }
+
+    /// 以純文本替換組合文本，光標在末尾，不顯示候選。
+    pub fn set_text(&mut self, text: &str) {
+        let len = text.len() as i32;
+        self.composition.preedit = Some(text.to_string());
+        self.composition.length = len;
+        self.composition.sel_start = len;
+        self.composition.sel_end = len;
+        self.composition.cursor_pos = len;
+        self.candidates.clear();
+        self.highlighted = None;
+    }
+
+    /// 候選框中每行的文本，如 `1. 你好 nǐ hǎo`.
+    pub fn rows(&self) -> Vec<String> {
+        self.candidates
+            .iter()
+            .map(|candidate| match &candidate.comment {
+                Some(comment) => format!("{}. {} {comment}", candidate.label, candidate.text),
+                None => format!("{}. {}", candidate.label, candidate.text),
+            })
+            .collect()
+    }
+}
+
+/// 渲染器可用的 Wayland 對象，尚未綁定的爲 `None`.
+pub struct Globals<'a> {
+    pub qh: &'a QueueHandle<Im>,
+    pub compositor: Option<&'a WlCompositor>,
+    pub shm: Option<&'a WlShm>,
+    pub input_method: Option<&'a ZwpInputMethodV2>,
+    pub fractional_scale_manager: Option<&'a WpFractionalScaleManagerV1>,
+    pub viewporter: Option<&'a WpViewporter>,
+}
+
+/// 合成器建議的表面縮放。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// `wl_surface.preferred_buffer_scale`
+    Integer(i32),
+    /// `wp_fractional_scale_v1.preferred_scale`, 分母爲 120
+    Fractional(u32),
+}
+
+/// 候選界面後端。
+pub trait CandidateRenderer {
+    /// 顯示模型，返回要設置到客戶端的預編輯文本，`None` 時不設置。
+    fn render(&mut self, model: &UiModel) -> Option<Preedit>;
+
+    /// Wayland 對象可用時調用，默認不處理。
+    fn bind(&mut self, _globals: &Globals) {}
+
+    /// 表面縮放變化時調用，默認不處理。
+    fn set_scale(&mut self, _scale: Scale) {}
+
+    /// 繪製候選的表面及其佈局，供指針命中測試。
+    fn pointer_target(&self) -> Option<(&WlSurface, &CandidateLayout)> {
+        None
+    }
+}
+
+/// 候選界面後端選項。
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Renderer {
+    /// 在預編輯文本中顯示候選
+    #[default]
+    Inline,
+    /// 在輸入法彈出窗口中繪製候選
+    Popup,
+    /// 通過控制套接字輸出 JSON, 供外部面板顯示
+    External,
+    /// 不顯示
+    None,
+}
+
+/// 按配置創建渲染器，失敗時退回內嵌顯示。
+pub fn renderer(config: &Config) -> Box<dyn CandidateRenderer> {
+    let inline = || Box::new(InlineRenderer::new(config.preedit.clone()));
+    match config.renderer {
+        Renderer::Inline => inline(),
+        Renderer::Popup => match PopupRenderer::new(&config.theme) {
+            Ok(renderer) => Box::new(renderer),
+            Err(err) => {
+                log::warn!("Fail to create popup renderer, use inline: {err}");
+                inline()
+            }
+        },
+        Renderer::External => match ExternalRenderer::new(&config.external) {
+            Ok(renderer) => Box::new(renderer),
+            Err(err) => {
+                log::warn!("Fail to create external renderer, use inline: {err}");
+                inline()
+            }
+        },
+        Renderer::None => Box::new(NullRenderer),
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    fs, io,
+    io::Write,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use super::{CandidateRenderer, UiModel};
+use crate::preedit::Preedit;
+
+/// 面板未讀取的數據超過此大小時斷開
+const MAX_PENDING: usize = 1 << 20;
+
+/// 外部面板配置。
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ExternalConfig {
+    /// 控制套接字路徑，默認爲 `$XDG_RUNTIME_DIR/wayime.sock`
+    pub socket: Option<PathBuf>,
+}
+
+/// 通過控制套接字向外部面板發送模型，每次更新一行 JSON.
+///
+/// 預編輯文本只含組合文本。新連接在接受時即收到最近一次的模型，
+/// 寫不完的部分留待下次更新，寫入失敗或讀取過慢的面板會被斷開。
+pub struct ExternalRenderer {
+    path: PathBuf,
+    panels: Arc<Mutex<Panels>>,
+    stop: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+/// 已連接的面板及最近一次的模型。
+#[derive(Default)]
+struct Panels {
+    connected: Vec<Panel>,
+    last: Option<String>,
+}
+
+impl Panels {
+    /// 向所有面板寫入，移除失敗的連接。
+    fn broadcast(&mut self, line: &str) {
+        self.connected.retain_mut(|panel| match panel.send(line) {
+            Ok(()) => true,
+            Err(err) => {
+                info!("Disconnect panel: {err}");
+                false
+            }
+        });
+    }
+}
+
+/// 非阻塞連接，保存未寫完的數據。
+struct Panel {
+    stream: UnixStream,
+    pending: Vec<u8>,
+}
+
+impl Panel {
+    fn new(stream: UnixStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            pending: Vec::new(),
+        })
+    }
+
+    /// 追加一行並儘量寫出。
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        self.pending.extend_from_slice(line.as_bytes());
+        self.flush()
+    }
+
+    /// 寫出到會阻塞爲止，剩餘部分保留。
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if self.pending.len() > MAX_PENDING {
+            return Err(io::Error::other("panel reads too slowly"));
+        }
+        Ok(())
+    }
+}
+
+impl ExternalRenderer {
+    pub fn new(config: &ExternalConfig) -> io::Result<Self> {
+        let path = match &config.socket {
+            Some(path) => path.clone(),
+            None => dirs::runtime_dir()
+                .ok_or_else(|| io::Error::other("no runtime dir"))?
+                .join("wayime.sock"),
+        };
+        remove_stale(&path)?;
+        let listener = UnixListener::bind(&path)?;
+        info!("Listen for panels on {}", path.display());
+
+        let panels = Arc::new(Mutex::new(Panels::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let acceptor = thread::Builder::new().name("panels".to_string()).spawn({
+            let panels = panels.clone();
+            let stop = stop.clone();
+            move || accept(listener, &panels, &stop)
+        })?;
+        Ok(Self {
+            path,
+            panels,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+}
+
+/// 清除上次運行留下的套接字。
+///
+/// 只刪除無人監聽的套接字，其他文件或仍在監聽的套接字報錯。
+fn remove_stale(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another process", path.display()),
+        ));
+    }
+    fs::remove_file(path)
+}
+
+/// 接受連接並立即發送最近一次的模型，直到 `stop` 被設置。
+fn accept(listener: UnixListener, panels: &Mutex<Panels>, stop: &AtomicBool) {
+    for stream in listener.incoming() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let panel = stream.and_then(Panel::new);
+        let mut panel = match panel {
+            Ok(panel) => panel,
+            Err(err) => {
+                warn!("Fail to accept panel: {err}");
+                continue;
+            }
+        };
+        let mut panels = panels.lock().unwrap();
+        if let Some(line) = &panels.last {
+            if let Err(err) = panel.send(line) {
+                info!("Disconnect panel: {err}");
+                continue;
+            }
+        }
+        panels.connected.push(panel);
+    }
+}
+
+impl CandidateRenderer for ExternalRenderer {
+    fn render(&mut self, model: &UiModel) -> Option<Preedit> {
+        let mut line = serde_json::to_string(model).unwrap();
+        line.push('\n');
+        let mut panels = self.panels.lock().unwrap();
+        panels.broadcast(&line);
+        panels.last = Some(line);
+        Some(Preedit::composition(model))
+    }
+}
+
+impl Drop for ExternalRenderer {
+    fn drop(&mut self) {
+        // 連接一次以喚醒阻塞在 accept 的線程
+        self.stop.store(true, Ordering::Relaxed);
+        if UnixStream::connect(&self.path).is_ok() {
+            if let Some(acceptor) = self.acceptor.take() {
+                let _ = acceptor.join();
+            }
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read};
+
+    use super::*;
+    use crate::ui::UiCandidate;
+
+    #[test]
+    fn panels_receive_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ExternalConfig {
+            socket: Some(dir.path().join("wayime.sock")),
+        };
+        let mut renderer = ExternalRenderer::new(&config).unwrap();
+        let model = UiModel {
+            candidates: vec![UiCandidate {
+                label: "1".to_string(),
+                text: "你好".to_string(),
+                comment: None,
+            }],
+            ..Default::default()
+        };
+
+        // 連接前的模型在連接時即補發
+        let preedit = renderer.render(&model).unwrap();
+        assert_eq!(preedit.text, "");
+        let panel = UnixStream::connect(dir.path().join("wayime.sock")).unwrap();
+        let mut lines = BufReader::new(panel).lines();
+        let first: serde_json::Value =
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(first["candidates"][0]["text"], "你好");
+
+        renderer.render(&UiModel::default());
+        let second: serde_json::Value =
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(second["candidates"], serde_json::json!([]));
+
+        drop(renderer);
+        assert!(!dir.path().join("wayime.sock").exists());
+    }
+
+    #[test]
+    fn keep_live_socket_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wayime.sock");
+        let config = ExternalConfig {
+            socket: Some(path.clone()),
+        };
+        let renderer = ExternalRenderer::new(&config).unwrap();
+        let err = ExternalRenderer::new(&config).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        drop(renderer);
+
+        // 無人監聽的套接字可以替換
+        drop(UnixListener::bind(&path).unwrap());
+        drop(ExternalRenderer::new(&config).unwrap());
+
+        fs::write(&path, "data").unwrap();
+        let err = ExternalRenderer::new(&config).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "data");
+    }
+
+    #[test]
+    fn partial_write_is_kept() {
+        let (stream, mut reader) = UnixStream::pair().unwrap();
+        let mut panel = Panel::new(stream).unwrap();
+        // 超過套接字緩衝區，一次寫不完
+        let line = "x".repeat(MAX_PENDING / 2) + "\n";
+        panel.send(&line).unwrap();
+        assert!(!panel.pending.is_empty());
+
+        let mut received = vec![0; line.len()];
+        let mut read = 0;
+        while read < line.len() {
+            read += reader.read(&mut received[read..]).unwrap();
+            panel.flush().unwrap();
+        }
+        assert!(panel.pending.is_empty());
+        assert_eq!(received, line.as_bytes());
+    }
+}
@@ -0,0 +1,19 @@
+use super::{CandidateRenderer, UiModel};
+use crate::preedit::{Preedit, PreeditConfig};
+
+/// 在預編輯文本中顯示候選。
+pub struct InlineRenderer {
+    config: PreeditConfig,
+}
+
+impl InlineRenderer {
+    pub fn new(config: PreeditConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl CandidateRenderer for InlineRenderer {
+    fn render(&mut self, model: &UiModel) -> Option<Preedit> {
+        Some(self.config.render(model))
+    }
+}
@@ -0,0 +1,11 @@
+use super::{CandidateRenderer, UiModel};
+use crate::preedit::Preedit;
+
+/// 不顯示任何內容，也不設置預編輯文本。
+pub struct NullRenderer;
+
+impl CandidateRenderer for NullRenderer {
+    fn render(&mut self, _: &UiModel) -> Option<Preedit> {
+        None
+    }
+}
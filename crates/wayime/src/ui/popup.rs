@@ -0,0 +1,487 @@
+use std::{
+    fs::{self, File},
+    io,
+    os::{fd::AsFd, unix::fs::FileExt},
+    path::Path,
+};
+
+use fontdue::{Font, FontSettings};
+use log::{info, warn};
+use wayland_client::{
+    protocol::{
+        wl_shm::{Format, WlShm},
+        wl_surface::WlSurface,
+    },
+    Proxy, QueueHandle,
+};
+use wayland_protocols::wp::{
+    fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1,
+    viewporter::client::wp_viewport::WpViewport,
+};
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_popup_surface_v2::ZwpInputPopupSurfaceV2;
+
+use super::{CandidateRenderer, Color, Colors, Globals, Orientation, Scale, ThemeConfig, UiModel};
+use crate::{
+    im::Im,
+    layout::{CandidateLayout, LayoutMetrics, Rect},
+    preedit::Preedit,
+    shm,
+};
+
+/// 在輸入法彈出窗口中繪製候選，預編輯文本只含組合文本。
+pub struct PopupRenderer {
+    font: Font,
+    metrics: LayoutMetrics,
+    colors: Colors,
+    orientation: Orientation,
+    border_width: f64,
+    border_radius: f64,
+    scale: Scale,
+    layout: CandidateLayout,
+    /// 上次顯示的模型，縮放變化時重繪
+    model: UiModel,
+    qh: Option<QueueHandle<Im>>,
+    shm: Option<WlShm>,
+    surface: Option<WlSurface>,
+    popup: Option<ZwpInputPopupSurfaceV2>,
+    fractional_scale: Option<WpFractionalScaleV1>,
+    viewport: Option<WpViewport>,
+}
+
+impl PopupRenderer {
+    pub fn new(config: &ThemeConfig) -> io::Result<Self> {
+        let path = config.font_path()?;
+        let font = load_font(&path)?;
+        info!("Popup font: {}", path.display());
+        Ok(Self {
+            font,
+            metrics: LayoutMetrics {
+                font_size: config.font_size as f64,
+                padding: config.padding,
+                ..Default::default()
+            },
+            colors: config.colors(),
+            orientation: config.layout,
+            border_width: config.border_width,
+            border_radius: config.border_radius,
+            scale: Scale::Integer(1),
+            layout: CandidateLayout::default(),
+            model: UiModel::default(),
+            qh: None,
+            shm: None,
+            surface: None,
+            popup: None,
+            fractional_scale: None,
+            viewport: None,
+        })
+    }
+
+    /// 緩衝區相對表面局部坐標的倍數。
+    fn factor(&self) -> f64 {
+        match self.scale {
+            Scale::Integer(factor) => factor.max(1) as f64,
+            Scale::Fractional(scale) => scale as f64 / 120.0,
+        }
+    }
+
+    fn text_width(&self, text: &str) -> f64 {
+        let size = self.metrics.font_size as f32;
+        text.chars()
+            .map(|c| self.font.metrics(c, size).advance_width as f64)
+            .sum()
+    }
+
+    /// 從 `pen` 起繪製文本，返回繪製後的 `pen`. 坐標均爲緩衝區坐標。
+    fn draw_text(
+        &self,
+        canvas: &mut Canvas,
+        pen: f64,
+        baseline: f64,
+        text: &str,
+        color: Color,
+    ) -> f64 {
+        let size = (self.metrics.font_size * self.factor()) as f32;
+        let mut pen = pen;
+        for c in text.chars() {
+            let (metrics, coverage) = self.font.rasterize(c, size);
+            let left = pen.round() as i64 + metrics.xmin as i64;
+            let top = baseline.round() as i64 - metrics.height as i64 - metrics.ymin as i64;
+            for (i, &alpha) in coverage.iter().enumerate() {
+                let x = left + (i % metrics.width.max(1)) as i64;
+                let y = top + (i / metrics.width.max(1)) as i64;
+                canvas.blend(x, y, color, alpha);
+            }
+            pen += metrics.advance_width as f64;
+        }
+        pen
+    }
+
+    /// 在矩形內左對齊依次繪製各段文本。
+    fn draw_line(&self, canvas: &mut Canvas, rect: Rect, segments: &[(&str, Color)]) {
+        let factor = self.factor();
+        let size = (self.metrics.font_size * factor) as f32;
+        let ascent = self
+            .font
+            .horizontal_line_metrics(size)
+            .map_or(size * 0.8, |metrics| metrics.ascent);
+        let rect = rect.scale(factor);
+        let baseline = rect.y + (rect.height - size as f64) / 2.0 + ascent as f64;
+        let mut pen = rect.x + self.metrics.padding * factor;
+        for (text, color) in segments {
+            pen = self.draw_text(canvas, pen, baseline, text, *color);
+        }
+    }
+
+    /// 繪製並提交緩衝區。
+    fn draw(&self, model: &UiModel) -> io::Result<()> {
+        let (Some(qh), Some(shm), Some(surface)) = (&self.qh, &self.shm, &self.surface) else {
+            return Ok(());
+        };
+        let colors = &self.colors;
+        let factor = self.factor();
+        let layout = &self.layout;
+        let (logical, (width, height)) = surface_size(layout, factor);
+        let mut canvas = Canvas::new(width, height);
+        let bounds = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: logical.0 as f64,
+            height: logical.1 as f64,
+        }
+        .scale(factor);
+        canvas.frame(
+            bounds,
+            self.border_radius * factor,
+            self.border_width * factor,
+            colors.background,
+            colors.border,
+        );
+        let inner = bounds.inset(self.border_width * factor);
+
+        let preedit = model.composition.preedit.as_deref().unwrap_or_default();
+        self.draw_line(&mut canvas, layout.preedit, &[(preedit, colors.text)]);
+        for (i, (rect, candidate)) in layout.candidates.iter().zip(&model.candidates).enumerate() {
+            let text = if Some(i) == model.highlighted {
+                canvas.fill_rect(rect.scale(factor).intersect(inner), colors.highlight);
+                colors.highlight_text
+            } else {
+                colors.text
+            };
+            // 與 `UiModel::rows` 的格式相同
+            let label = format!("{}. ", candidate.label);
+            let comment = candidate
+                .comment
+                .as_ref()
+                .map(|comment| format!(" {comment}"))
+                .unwrap_or_default();
+            self.draw_line(
+                &mut canvas,
+                *rect,
+                &[
+                    (&label, colors.label),
+                    (&candidate.text, text),
+                    (&comment, colors.comment),
+                ],
+            );
+        }
+
+        let file = canvas.to_file()?;
+        let (width, height) = (canvas.width as i32, canvas.height as i32);
+        let pool = shm.create_pool(file.as_fd(), width * height * 4, qh, ());
+        let buffer = pool.create_buffer(0, width, height, width * 4, Format::Argb8888, qh, ());
+        pool.destroy();
+        match (self.scale, &self.viewport) {
+            (Scale::Fractional(_), Some(viewport)) => {
+                viewport.set_destination(logical.0, logical.1);
+            }
+            (Scale::Integer(factor), _) if surface.version() >= 3 => {
+                surface.set_buffer_scale(factor.max(1));
+            }
+            _ => {}
+        }
+        surface.attach(Some(&buffer), 0, 0);
+        surface.damage_buffer(0, 0, width, height);
+        surface.commit();
+        Ok(())
+    }
+
+    /// 卸下緩衝區以隱藏彈出窗口。
+    fn hide(&self) {
+        if let Some(surface) = &self.surface {
+            surface.attach(None, 0, 0);
+            surface.commit();
+        }
+    }
+}
+
+impl CandidateRenderer for PopupRenderer {
+    fn render(&mut self, model: &UiModel) -> Option<Preedit> {
+        self.model = model.clone();
+        if model.candidates.is_empty() && model.notice.is_none() {
+            self.layout = CandidateLayout::default();
+            self.hide();
+        } else {
+            let preedit = model.composition.preedit.as_deref().unwrap_or_default();
+            let measure = |text: &str| self.text_width(text);
+            let rows = model.rows();
+            self.layout = match self.orientation {
+                Orientation::Vertical => {
+                    CandidateLayout::vertical(self.metrics, measure, preedit, &rows)
+                }
+                Orientation::Horizontal => {
+                    CandidateLayout::horizontal(self.metrics, measure, preedit, &rows)
+                }
+            };
+            if let Err(err) = self.draw(model) {
+                warn!("Fail to draw popup: {err}");
+            }
+        }
+        Some(Preedit::composition(model))
+    }
+
+    fn bind(&mut self, globals: &Globals) {
+        self.qh.get_or_insert_with(|| globals.qh.clone());
+        if self.shm.is_none() {
+            self.shm = globals.shm.cloned();
+        }
+        if self.surface.is_none() {
+            if let (Some(compositor), Some(input_method)) =
+                (globals.compositor, globals.input_method)
+            {
+                let surface = compositor.create_surface(globals.qh, ());
+                self.popup = Some(input_method.get_input_popup_surface(&surface, globals.qh, ()));
+                self.surface = Some(surface);
+            }
+        }
+        // 分數縮放需要 viewporter 設置表面大小
+        if self.fractional_scale.is_none() {
+            if let (Some(surface), Some(manager), Some(viewporter)) = (
+                &self.surface,
+                globals.fractional_scale_manager,
+                globals.viewporter,
+            ) {
+                self.fractional_scale = Some(manager.get_fractional_scale(surface, globals.qh, ()));
+                self.viewport = Some(viewporter.get_viewport(surface, globals.qh, ()));
+            }
+        }
+    }
+
+    fn set_scale(&mut self, scale: Scale) {
+        // 有分數縮放時忽略整數縮放
+        if matches!(scale, Scale::Integer(_)) && self.fractional_scale.is_some() {
+            return;
+        }
+        if scale == self.scale {
+            return;
+        }
+        self.scale = scale;
+        if !self.layout.candidates.is_empty() || self.model.notice.is_some() {
+            if let Err(err) = self.draw(&self.model) {
+                warn!("Fail to draw popup: {err}");
+            }
+        }
+    }
+
+    fn pointer_target(&self) -> Option<(&WlSurface, &CandidateLayout)> {
+        self.surface.as_ref().map(|surface| (surface, &self.layout))
+    }
+}
+
+impl Drop for PopupRenderer {
+    fn drop(&mut self) {
+        if let Some(viewport) = &self.viewport {
+            viewport.destroy();
+        }
+        if let Some(fractional_scale) = &self.fractional_scale {
+            fractional_scale.destroy();
+        }
+        if let Some(popup) = &self.popup {
+            popup.destroy();
+        }
+        if let Some(surface) = &self.surface {
+            surface.destroy();
+        }
+    }
+}
+
+fn load_font(path: &Path) -> io::Result<Font> {
+    let data = fs::read(path)?;
+    Font::from_bytes(data, FontSettings::default())
+        .map_err(|err| io::Error::other(format!("{}: {err}", path.display())))
+}
+
+/// 預乘 alpha 的 ARGB8888 像素緩衝。
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u32>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let x0 = (rect.x.max(0.0) as usize).min(self.width);
+        let x1 = ((rect.x + rect.width).max(0.0).ceil() as usize).min(self.width);
+        let y0 = (rect.y.max(0.0) as usize).min(self.height);
+        let y1 = ((rect.y + rect.height).max(0.0).ceil() as usize).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x as i64, y as i64, color, 255);
+            }
+        }
+    }
+
+    /// 以圓角矩形覆蓋整個緩衝區，邊框寬度爲 `border` 且畫在矩形內，角外透明。
+    fn frame(&mut self, rect: Rect, radius: f64, border: f64, background: Color, edge: Color) {
+        let inner = rect.inset(border);
+        let inner_radius = (radius - border).max(0.0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                let outer = coverage(rect, radius, px, py);
+                let inside = coverage(inner, inner_radius, px, py);
+                let pixel = &mut self.pixels[y * self.width + x];
+                *pixel = add(
+                    fade(edge.premultiplied(), outer - inside),
+                    fade(background.premultiplied(), inside),
+                );
+            }
+        }
+    }
+
+    /// 按覆蓋率將顏色疊加在像素上。
+    fn blend(&mut self, x: i64, y: i64, color: Color, alpha: u8) {
+        if alpha == 0 || x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let pixel = &mut self.pixels[y as usize * self.width + x as usize];
+        let src = fade(color.premultiplied(), alpha as f64 / 255.0);
+        *pixel = add(src, fade(*pixel, 1.0 - (src >> 24) as f64 / 255.0));
+    }
+
+    /// 寫入臨時文件，供共享內存池使用。
+    fn to_file(&self) -> io::Result<File> {
+        let bytes = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_le_bytes())
+            .collect::<Vec<_>>();
+        let file = shm::anonymous_file()?;
+        file.write_all_at(&bytes, 0)?;
+        Ok(file)
+    }
+}
+
+/// 佈局的表面大小和緩衝區大小。
+///
+/// 先將表面大小取整再乘縮放，整數縮放時緩衝區恰爲表面的整數倍，
+/// 否則合成器會以 `invalid_size` 斷開連接。
+fn surface_size(layout: &CandidateLayout, factor: f64) -> ((i32, i32), (usize, usize)) {
+    let (width, height) = (layout.width.ceil(), layout.height.ceil());
+    (
+        (width as i32, height as i32),
+        (
+            (width * factor).ceil() as usize,
+            (height * factor).ceil() as usize,
+        ),
+    )
+}
+
+/// 像素中心在圓角矩形內的覆蓋率，邊緣一像素內線性過渡。
+fn coverage(rect: Rect, radius: f64, x: f64, y: f64) -> f64 {
+    let radius = radius.min(rect.width / 2.0).min(rect.height / 2.0).max(0.0);
+    let qx = (x - rect.x - rect.width / 2.0).abs() - (rect.width / 2.0 - radius);
+    let qy = (y - rect.y - rect.height / 2.0).abs() - (rect.height / 2.0 - radius);
+    let distance = qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius;
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// 按比例縮小預乘顏色的各通道。
+fn fade(pixel: u32, by: f64) -> u32 {
+    let by = by.clamp(0.0, 1.0);
+    let channel = |shift: u32| (((pixel >> shift & 0xff) as f64 * by).round() as u32) << shift;
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+/// 逐通道相加預乘顏色。
+fn add(a: u32, b: u32) -> u32 {
+    let channel = |shift: u32| ((a >> shift & 0xff) + (b >> shift & 0xff)).min(0xff) << shift;
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn canvas_blend() {
+        let mut canvas = Canvas::new(4, 2);
+        let black = Color(0xff000000);
+        canvas.frame(rect(0.0, 0.0, 4.0, 2.0), 0.0, 0.0, black, black);
+        canvas.fill_rect(rect(1.0, 1.0, 2.0, 5.0), Color(0xff0000ff));
+        assert_eq!(
+            canvas.pixels[4..],
+            [0xff000000, 0xff0000ff, 0xff0000ff, 0xff000000]
+        );
+        let white = Color(0xffffffff);
+        canvas.blend(0, 0, white, 255);
+        canvas.blend(1, 0, white, 0);
+        canvas.blend(9, 9, white, 255);
+        canvas.blend(2, 0, Color(0x80ffffff), 255);
+        assert_eq!(canvas.pixels[..3], [0xffffffff, 0xff000000, 0xff808080]);
+    }
+
+    #[test]
+    fn integer_scale_keeps_buffer_divisible() {
+        let layout = CandidateLayout {
+            width: 100.3,
+            height: 41.5,
+            ..Default::default()
+        };
+        assert_eq!(surface_size(&layout, 2.0), ((101, 42), (202, 84)));
+        // 分數縮放由 viewport 設置表面大小
+        assert_eq!(surface_size(&layout, 1.5), ((101, 42), (152, 63)));
+    }
+
+    #[test]
+    fn text_uses_font_advance() {
+        let config = ThemeConfig {
+            font_file: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/src/im/tests/box.ttf").into()),
+            ..Default::default()
+        };
+        let renderer = PopupRenderer::new(&config).unwrap();
+        // 方框字體每字寬 1 em
+        assert_eq!(renderer.text_width("a中"), 2.0 * config.font_size as f64);
+        let mut canvas = Canvas::new(40, 20);
+        let pen = renderer.draw_text(&mut canvas, 0.0, 16.0, "a", Color(0xffffffff));
+        assert_eq!(pen, config.font_size as f64);
+        assert!(canvas.pixels.contains(&0xffffffff));
+    }
+
+    #[test]
+    fn canvas_frame() {
+        let mut canvas = Canvas::new(10, 10);
+        let (background, border) = (Color(0xff0000ff), Color(0x80ff0000));
+        canvas.frame(rect(0.0, 0.0, 10.0, 10.0), 4.0, 1.0, background, border);
+        // 圓角外透明，邊框半透明，內部爲背景
+        assert_eq!(canvas.pixels[0], 0);
+        assert_eq!(canvas.pixels[5], 0x80800000);
+        assert_eq!(canvas.pixels[5 * 10 + 5], 0xff0000ff);
+    }
+}
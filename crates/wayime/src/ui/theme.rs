@@ -0,0 +1,405 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use log::warn;
+use serde::{Deserialize, Deserializer};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// 找不到配置的字體時依次嘗試。
+const FALLBACK_FONTS: &[&str] = &[
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+];
+
+/// 非預乘的 ARGB 顏色。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color(pub u32);
+
+impl Color {
+    pub fn alpha(self) -> u32 {
+        self.0 >> 24
+    }
+
+    /// 預乘 alpha, 即 `wl_shm` 的像素格式。
+    pub fn premultiplied(self) -> u32 {
+        let alpha = self.alpha();
+        let channel = |shift: u32| ((self.0 >> shift & 0xff) * alpha / 255) << shift;
+        alpha << 24 | channel(16) | channel(8) | channel(0)
+    }
+
+    /// 按 Rime 前端的 `color_format` 解析整數顏色，默認爲 `0xaabbggrr`.
+    ///
+    /// 不超過 24 位時視爲不透明。
+    fn from_rime(value: u32, format: &str) -> Self {
+        let opaque = value <= 0xffffff;
+        let (a, r, g, b) = match format {
+            "argb" => (value >> 24, value >> 16, value >> 8, value),
+            "rgba" if opaque => (0xff, value >> 16, value >> 8, value),
+            "rgba" => (value, value >> 24, value >> 16, value >> 8),
+            _ => (value >> 24, value, value >> 8, value >> 16),
+        };
+        let a = if opaque { 0xff } else { a & 0xff };
+        Self(a << 24 | (r & 0xff) << 16 | (g & 0xff) << 8 | (b & 0xff))
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// `#rrggbb` 或 `#rrggbbaa`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| matches!(hex.len(), 6 | 8))
+            .ok_or_else(|| format!("expect `#rrggbb` or `#rrggbbaa`, found `{s}`"))?;
+        let value = u32::from_str_radix(hex, 16).map_err(|err| format!("`{s}`: {err}"))?;
+        Ok(match hex.len() {
+            6 => Self(0xff000000 | value),
+            _ => Self(value.rotate_right(8)),
+        })
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:06x}{:02x}", self.0 & 0xffffff, self.alpha())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 候選窗口的顏色。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Colors {
+    pub background: Color,
+    pub text: Color,
+    pub highlight: Color,
+    pub highlight_text: Color,
+    pub comment: Color,
+    pub label: Color,
+    pub border: Color,
+}
+
+/// 覆蓋主題中的部分顏色。
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ColorOverrides {
+    pub background: Option<Color>,
+    pub text: Option<Color>,
+    pub highlight: Option<Color>,
+    pub highlight_text: Option<Color>,
+    pub comment: Option<Color>,
+    pub label: Option<Color>,
+    pub border: Option<Color>,
+}
+
+impl Colors {
+    fn apply(&mut self, overrides: &ColorOverrides) {
+        let fields = [
+            (&mut self.background, overrides.background),
+            (&mut self.text, overrides.text),
+            (&mut self.highlight, overrides.highlight),
+            (&mut self.highlight_text, overrides.highlight_text),
+            (&mut self.comment, overrides.comment),
+            (&mut self.label, overrides.label),
+            (&mut self.border, overrides.border),
+        ];
+        for (color, value) in fields {
+            if let Some(value) = value {
+                *color = value;
+            }
+        }
+    }
+
+    /// 內置主題。
+    pub fn builtin(name: &str) -> Option<Self> {
+        let colors =
+            |[background, text, highlight, highlight_text, comment, label, border]: [u32; 7]| {
+                Self {
+                    background: Color(background),
+                    text: Color(text),
+                    highlight: Color(highlight),
+                    highlight_text: Color(highlight_text),
+                    comment: Color(comment),
+                    label: Color(label),
+                    border: Color(border),
+                }
+            };
+        Some(match name {
+            "dark" => colors([
+                0xff2b2b2b, 0xffeeeeee, 0xff3a6ea5, 0xffffffff, 0xff999999, 0xff8fb3d9, 0xff444444,
+            ]),
+            "light" => colors([
+                0xfffafafa, 0xff202020, 0xff2f6fdd, 0xffffffff, 0xff808080, 0xff2f6fdd, 0xffd0d0d0,
+            ]),
+            "nord" => colors([
+                0xff2e3440, 0xffeceff4, 0xff5e81ac, 0xffeceff4, 0xff7b88a1, 0xff88c0d0, 0xff4c566a,
+            ]),
+            _ => return None,
+        })
+    }
+}
+
+/// 候選排列方向。
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Orientation {
+    #[default]
+    Vertical,
+    Horizontal,
+}
+
+/// 從 Rime 前端配置導入的配色，如 `weasel.yaml` 或 `squirrel.yaml`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RimeColorScheme {
+    /// 開頭的 `~` 展開爲主目錄
+    pub file: PathBuf,
+    /// `preset_color_schemes` 中的名稱，默認取 `style/color_scheme`
+    #[serde(default)]
+    pub scheme: Option<String>,
+}
+
+/// 候選窗口主題配置。
+///
+/// ```toml
+/// [theme]
+/// name = "nord"
+/// font = "Noto Sans CJK SC"
+/// font-size = 18
+/// layout = "horizontal"
+/// border-radius = 6
+/// rime = { file = "~/.config/wayime/rime/weasel.yaml" }
+///
+/// [theme.colors]
+/// highlight = "#bf616a"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ThemeConfig {
+    /// 內置主題：`dark`, `light` 或 `nord`
+    pub name: String,
+    /// 字體族名，經 fontconfig 查找
+    pub font: Option<String>,
+    /// 字體文件，優先於 `font`, 開頭的 `~` 展開爲主目錄
+    pub font_file: Option<PathBuf>,
+    pub font_size: f32,
+    pub layout: Orientation,
+    pub padding: f64,
+    pub border_width: f64,
+    pub border_radius: f64,
+    /// 導入的配色，覆蓋內置主題
+    pub rime: Option<RimeColorScheme>,
+    /// 覆蓋以上配色
+    pub colors: ColorOverrides,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "dark".to_string(),
+            font: None,
+            font_file: None,
+            font_size: 16.0,
+            layout: Orientation::default(),
+            padding: 6.0,
+            border_width: 1.0,
+            border_radius: 4.0,
+            rime: None,
+            colors: ColorOverrides::default(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// 依次應用內置主題、Rime 配色和覆蓋的顏色，出錯的部分跳過。
+    pub fn colors(&self) -> Colors {
+        let mut colors = Colors::builtin(&self.name).unwrap_or_else(|| {
+            warn!("Unknown theme `{}`, use dark", self.name);
+            Colors::builtin("dark").unwrap()
+        });
+        if let Some(rime) = &self.rime {
+            match import_rime(&expand_home(&rime.file), rime.scheme.as_deref()) {
+                Ok(overrides) => colors.apply(&overrides),
+                Err(err) => warn!("Fail to import {}: {err}", rime.file.display()),
+            }
+        }
+        colors.apply(&self.colors);
+        colors
+    }
+
+    /// 字體文件路徑，依次取 `font-file`, fontconfig 匹配的 `font` 和常見路徑。
+    pub fn font_path(&self) -> io::Result<PathBuf> {
+        if let Some(path) = &self.font_file {
+            return Ok(expand_home(path));
+        }
+        if let Some(family) = &self.font {
+            match fc_match("fc-match", family) {
+                Ok(path) => return Ok(path),
+                Err(err) => warn!("Fail to find font `{family}`, use a default font: {err}"),
+            }
+        }
+        FALLBACK_FONTS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+            .ok_or_else(|| io::Error::other("no font found, set `theme.font`"))
+    }
+}
+
+/// 將開頭的 `~` 展開爲主目錄。
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 通過 `fc-match` 查找字體文件。
+fn fc_match(program: &str, family: &str) -> io::Result<PathBuf> {
+    let output = Command::new(program)
+        .args(["--format=%{file}", family])
+        .output()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                err.kind(),
+                format!("`{program}` not found, install fontconfig or set `theme.font-file`"),
+            ),
+            _ => err,
+        })?;
+    let path = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() || path.is_empty() {
+        return Err(io::Error::other("no match"));
+    }
+    Ok(PathBuf::from(path))
+}
+
+/// 讀取 Rime 前端配置中的配色方案。
+fn import_rime(path: &Path, scheme: Option<&str>) -> io::Result<ColorOverrides> {
+    let text = fs::read_to_string(path)?;
+    let docs = YamlLoader::load_from_str(&text).map_err(io::Error::other)?;
+    let doc = docs.first().unwrap_or(&Yaml::BadValue);
+    let name = scheme
+        .or_else(|| doc["style"]["color_scheme"].as_str())
+        .ok_or_else(|| io::Error::other("no `style/color_scheme`"))?;
+    let scheme = &doc["preset_color_schemes"][name];
+    if scheme.is_badvalue() {
+        return Err(io::Error::other(format!("no color scheme `{name}`")));
+    }
+    Ok(rime_colors(scheme))
+}
+
+fn rime_colors(scheme: &Yaml) -> ColorOverrides {
+    let format = scheme["color_format"].as_str().unwrap_or("abgr");
+    let color = |key: &str| {
+        let value = match &scheme[key] {
+            Yaml::Integer(value) => u32::try_from(*value).ok(),
+            Yaml::String(value) => u32::from_str_radix(value.trim_start_matches("0x"), 16).ok(),
+            _ => None,
+        };
+        value.map(|value| Color::from_rime(value, format))
+    };
+    ColorOverrides {
+        background: color("back_color"),
+        text: color("candidate_text_color").or_else(|| color("text_color")),
+        highlight: color("hilited_candidate_back_color"),
+        highlight_text: color("hilited_candidate_text_color"),
+        comment: color("comment_text_color"),
+        label: color("label_color"),
+        border: color("border_color"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!("#2e3440".parse::<Color>(), Ok(Color(0xff2e3440)));
+        assert_eq!("#2e344080".parse::<Color>(), Ok(Color(0x802e3440)));
+        assert!("2e3440".parse::<Color>().is_err());
+        assert_eq!(Color(0x802e3440).to_string(), "#2e344080");
+        assert_eq!(Color(0x80ff0000).premultiplied(), 0x80800000);
+        // Rime 默認爲 BGR 順序
+        assert_eq!(Color::from_rime(0x4030ff, "abgr"), Color(0xffff3040));
+        assert_eq!(Color::from_rime(0x804030ff, "abgr"), Color(0x80ff3040));
+        assert_eq!(Color::from_rime(0x80ff3040, "argb"), Color(0x80ff3040));
+        assert_eq!(Color::from_rime(0xff304080, "rgba"), Color(0x80ff3040));
+    }
+
+    #[test]
+    fn import_weasel_scheme() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            br#"
+style:
+  color_scheme: aqua
+preset_color_schemes:
+  aqua:
+    name: Aqua
+    back_color: 0xeeeeec
+    text_color: 0x606060
+    hilited_candidate_back_color: 0xc0915e
+    hilited_candidate_text_color: "0xffffff"
+    label_color: 0x888888
+"#,
+        )
+        .unwrap();
+        let config = ThemeConfig {
+            rime: Some(RimeColorScheme {
+                file: file.path().to_path_buf(),
+                scheme: None,
+            }),
+            colors: ColorOverrides {
+                label: Some(Color(0xff123456)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let colors = config.colors();
+        assert_eq!(colors.background, Color(0xffeceeee));
+        assert_eq!(colors.text, Color(0xff606060));
+        assert_eq!(colors.highlight, Color(0xff5e91c0));
+        assert_eq!(colors.highlight_text, Color(0xffffffff));
+        // 未導入的保留內置主題，覆蓋優先
+        assert_eq!(colors.comment, Colors::builtin("dark").unwrap().comment);
+        assert_eq!(colors.label, Color(0xff123456));
+    }
+
+    #[test]
+    fn expand_home_dir() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            expand_home(Path::new("~/.config/wayime/weasel.yaml")),
+            home.join(".config/wayime/weasel.yaml")
+        );
+        assert_eq!(expand_home(Path::new("/etc/~")), Path::new("/etc/~"));
+        assert_eq!(expand_home(Path::new("~user/a")), Path::new("~user/a"));
+    }
+
+    #[test]
+    fn missing_fc_match() {
+        let err = fc_match("wayime-no-such-fc-match", "Sans").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("install fontconfig"));
+    }
+}